@@ -0,0 +1,237 @@
+//! The environment markup is rendered into, which `@media` rules are evaluated against.
+use std::collections::HashMap;
+use std::fmt;
+
+/// Whether the terminal has a light or dark background.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// The format markup is rendered to.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Output {
+    /// Text with ANSI escape codes.
+    #[default]
+    Ansi,
+    /// Text with all styling removed.
+    Plain,
+    /// HTML. Markup is rendered as plain text, for the caller to mark up; this only selects
+    /// `@media (output: html)` rules.
+    Html,
+}
+
+/// A description of where markup is rendered to.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RenderContext {
+    /// The number of colours the output supports, e.g. 16, 256 or 16777216 for true colour.
+    pub colors: u32,
+    /// The terminal's background, if known.
+    pub color_scheme: Option<ColorScheme>,
+    /// Environment variables, for `@media (env: NAME)`.
+    pub env: HashMap<String, String>,
+    /// The output format.
+    pub output: Output,
+    /// Whether the output can display Unicode, or should use the ASCII fallbacks for generated
+    /// content.
+    pub unicode: bool,
+}
+
+impl Default for RenderContext {
+    /// A 16 colour ANSI terminal with an unknown background and no environment.
+    fn default() -> RenderContext {
+        RenderContext {
+            colors: 16,
+            color_scheme: None,
+            env: HashMap::new(),
+            output: Output::Ansi,
+            unicode: true,
+        }
+    }
+}
+
+impl RenderContext {
+    /// Detect the context from the process environment (`TERM`, `COLORTERM`, `COLORFGBG`, the
+    /// locale and `colored`'s own `NO_COLOR`/`CLICOLOR` handling).
+    pub fn detect() -> RenderContext {
+        RenderContext::from_env(std::env::vars().collect())
+    }
+
+    pub(crate) fn from_env(env: HashMap<String, String>) -> RenderContext {
+        let var = |name: &str| env.get(name).map(String::as_str).unwrap_or("");
+        let colors =
+            if !colored::control::SHOULD_COLORIZE.should_colorize() || var("TERM") == "dumb" {
+                0
+            } else if matches!(var("COLORTERM"), "truecolor" | "24bit") {
+                1 << 24
+            } else if var("TERM").contains("256color") {
+                256
+            } else {
+                16
+            };
+        // COLORFGBG is "foreground;background" (sometimes with a middle field) using ANSI
+        // colour numbers, where 7 and 9-15 are light backgrounds.
+        let color_scheme = var("COLORFGBG")
+            .rsplit(';')
+            .next()
+            .and_then(|background| background.parse::<u8>().ok())
+            .map(|background| match background {
+                7 | 9..=15 => ColorScheme::Light,
+                _ => ColorScheme::Dark,
+            });
+        // The first locale variable that is set decides the character set.
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .map(|name| var(name))
+            .find(|value| !value.is_empty());
+        let unicode = locale.is_none_or(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        });
+        RenderContext {
+            colors,
+            color_scheme,
+            env,
+            output: Output::Ansi,
+            unicode,
+        }
+    }
+}
+
+#[test]
+fn test_from_env() {
+    colored::control::set_override(true);
+    let context = |vars: &[(&str, &str)]| {
+        RenderContext::from_env(
+            vars.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    };
+    assert_eq!(context(&[("TERM", "xterm")]).colors, 16);
+    assert_eq!(context(&[("TERM", "xterm-256color")]).colors, 256);
+    assert_eq!(context(&[("COLORTERM", "truecolor")]).colors, 1 << 24);
+    assert_eq!(context(&[("TERM", "dumb")]).colors, 0);
+    assert_eq!(
+        context(&[("COLORFGBG", "15;0")]).color_scheme,
+        Some(ColorScheme::Dark)
+    );
+    assert_eq!(
+        context(&[("COLORFGBG", "0;default;15")]).color_scheme,
+        Some(ColorScheme::Light)
+    );
+    assert_eq!(context(&[]).color_scheme, None);
+    assert!(context(&[]).unicode);
+    assert!(context(&[("LANG", "en_US.UTF-8")]).unicode);
+    assert!(!context(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]).unicode);
+}
+
+/// A comparison in a range media feature such as `(colors >= 256)`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// A single parenthesised `@media` condition.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum MediaCondition {
+    Colors(Comparison, u32),
+    ColorScheme(ColorScheme),
+    /// True if the environment variable is set and not empty.
+    Env(String),
+    Output(Output),
+}
+
+/// An `@media` query: conditions joined with `and`.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct MediaQuery(pub(crate) Vec<MediaCondition>);
+
+impl MediaCondition {
+    pub(crate) fn matches(&self, context: &RenderContext) -> bool {
+        match self {
+            MediaCondition::Colors(comparison, value) => match comparison {
+                Comparison::Less => context.colors < *value,
+                Comparison::LessOrEqual => context.colors <= *value,
+                Comparison::Equal => context.colors == *value,
+                Comparison::GreaterOrEqual => context.colors >= *value,
+                Comparison::Greater => context.colors > *value,
+            },
+            MediaCondition::ColorScheme(scheme) => context.color_scheme == Some(*scheme),
+            MediaCondition::Env(name) => context.env.get(name).is_some_and(|v| !v.is_empty()),
+            MediaCondition::Output(output) => context.output == *output,
+        }
+    }
+}
+
+impl MediaQuery {
+    pub(crate) fn matches(&self, context: &RenderContext) -> bool {
+        self.0.iter().all(|condition| condition.matches(context))
+    }
+}
+
+impl fmt::Display for MediaCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MediaCondition::Colors(comparison, value) => {
+                let comparison = match comparison {
+                    Comparison::Less => "<",
+                    Comparison::LessOrEqual => "<=",
+                    Comparison::Equal => "=",
+                    Comparison::GreaterOrEqual => ">=",
+                    Comparison::Greater => ">",
+                };
+                write!(f, "(colors {} {})", comparison, value)
+            }
+            MediaCondition::ColorScheme(ColorScheme::Light) => {
+                write!(f, "(prefers-color-scheme: light)")
+            }
+            MediaCondition::ColorScheme(ColorScheme::Dark) => {
+                write!(f, "(prefers-color-scheme: dark)")
+            }
+            MediaCondition::Env(name) => write!(f, "(env: {})", name),
+            MediaCondition::Output(output) => {
+                let output = match output {
+                    Output::Ansi => "ansi",
+                    Output::Plain => "plain",
+                    Output::Html => "html",
+                };
+                write!(f, "(output: {})", output)
+            }
+        }
+    }
+}
+
+impl fmt::Display for MediaQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let conditions: Vec<String> = self.0.iter().map(MediaCondition::to_string).collect();
+        write!(f, "{}", conditions.join(" and "))
+    }
+}
+
+#[test]
+fn test_media_query() {
+    let mut context = RenderContext {
+        colors: 256,
+        ..Default::default()
+    };
+    context.env.insert("CI".to_string(), "true".to_string());
+    let query = MediaQuery;
+    assert!(query(vec![MediaCondition::Colors(
+        Comparison::GreaterOrEqual,
+        256
+    )])
+    .matches(&context));
+    assert!(!query(vec![MediaCondition::Colors(Comparison::Greater, 256)]).matches(&context));
+    assert!(query(vec![
+        MediaCondition::Env("CI".to_string()),
+        MediaCondition::Output(Output::Ansi)
+    ])
+    .matches(&context));
+    assert!(!query(vec![MediaCondition::Env("GITHUB_ACTIONS".to_string())]).matches(&context));
+    assert!(!query(vec![MediaCondition::ColorScheme(ColorScheme::Dark)]).matches(&context));
+}
@@ -0,0 +1,289 @@
+//! Checks that text stays readable against its background.
+use crate::context::RenderContext;
+use crate::declarations::{ColorExpr, Property};
+use crate::lint::Diagnostic;
+use crate::palette::{contrast_ratio, from_hsl, to_hsl};
+use crate::styles::*;
+use crate::StyleSheet;
+
+type Rgb = (u8, u8, u8);
+
+/// `foreground`, lightened or darkened, whichever contrasts more with `background`, until its
+/// contrast ratio reaches `min_ratio` or it is white or black.
+fn nudge(foreground: Rgb, background: Rgb, min_ratio: f32) -> Rgb {
+    let (hue, saturation, lightness) = to_hsl(foreground);
+    let step =
+        if contrast_ratio((255, 255, 255), background) >= contrast_ratio((0, 0, 0), background) {
+            0.01
+        } else {
+            -0.01
+        };
+    let mut nudged = foreground;
+    for count in 1..=100 {
+        let lightness = (lightness + step * count as f32).clamp(0.0, 1.0);
+        nudged = from_hsl((hue, saturation, lightness));
+        if contrast_ratio(nudged, background) >= min_ratio {
+            break;
+        }
+    }
+    nudged
+}
+
+impl StyleSheet<'_> {
+    /// The colours text with `style` is drawn in, using the palette's foreground and background
+    /// where it sets none, or `None` if the text is hidden or coloured by a gradient.
+    fn drawn_colors(&self, style: &Style) -> Option<(Rgb, Rgb)> {
+        if style.styles.contains(&Styles::Hidden) || style.gradient.is_some() {
+            return None;
+        }
+        let palette = self.palette();
+        let foreground = style
+            .foreground
+            .map_or(palette.foreground(), |c| palette.rgb(c));
+        let background = style
+            .background
+            .map_or(palette.background(), |c| palette.rgb(c));
+        Some(match style.styles.contains(&Styles::Reversed) {
+            true => (background, foreground),
+            false => (foreground, background),
+        })
+    }
+
+    /// The tags with a rule, other than `*`, in the order of their first rule.
+    fn rule_tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = Vec::new();
+        for rule in &self.rules {
+            if let Some(tag) = rule.selector.names.last() {
+                if tag != "*" && !tags.contains(&tag.as_ref()) {
+                    tags.push(tag);
+                }
+            }
+        }
+        tags
+    }
+
+    /// The indices of the rule and declaration that give `tag` its cascaded foreground in
+    /// `context`, if that is a colour rather than a keyword or gradient.
+    fn foreground_declaration(&self, tag: &str, context: &RenderContext) -> Option<(usize, usize)> {
+        let mut rules: Vec<usize> = (0..self.rules.len())
+            .filter(|&index| self.rules[index].applies_to(&[tag], None, context))
+            .collect();
+        // Ordered as the cascade orders them, so the last rule setting a foreground wins.
+        rules.sort_by_key(|&index| self.rules[index].selector.specificity());
+        let important = rules
+            .iter()
+            .any(|&index| self.rules[index].important_style.foreground.is_some());
+        let rule = rules.into_iter().rev().find(|&index| {
+            let rule = &self.rules[index];
+            match important {
+                true => rule.important_style.foreground.is_some(),
+                false => rule.style.foreground.is_some(),
+            }
+        })?;
+        let declarations = &self.rules[rule].declarations;
+        let declaration = declarations.iter().rposition(|declaration| {
+            declaration.important == important && declaration.property.name() == "foreground"
+        })?;
+        matches!(declarations[declaration].property, Property::Foreground(_))
+            .then_some((rule, declaration))
+    }
+
+    /// Find text whose colours have a WCAG contrast ratio below `min_ratio` when rendered in
+    /// `context`, e.g. 4.5 for normal text. Colours a tag does not set are the palette's, so
+    /// set a [`Palette`](crate::palette::Palette) with the terminal's background to check a
+    /// stylesheet against it.
+    ///
+    /// Every tag with a rule is checked on its own and nested directly inside every other, so
+    /// a foreground that only clashes with an enclosing tag's background is found too.
+    pub fn check_contrast(&self, min_ratio: f32, context: &RenderContext) -> Vec<Diagnostic> {
+        let tags = self.rule_tags();
+        let colors = |stack: &[&str]| {
+            let styles = (1..=stack.len()).map(|depth| self.style(&stack[..depth], context));
+            self.drawn_colors(&Style::resolve(&styles.collect()))
+        };
+        let mut diagnostics = Vec::new();
+        for &inner in &tags {
+            let alone = colors(&[inner]);
+            let nested = tags.iter().filter(|&&outer| outer != inner);
+            let stacks = nested.map(|&outer| vec![outer, inner]);
+            for stack in std::iter::once(vec![inner]).chain(stacks) {
+                let Some((foreground, background)) = colors(&stack) else {
+                    continue;
+                };
+                // Nested tags are only reported if nesting gives them different colours.
+                if stack.len() > 1
+                    && [alone, colors(&stack[..1])].contains(&Some((foreground, background)))
+                {
+                    continue;
+                }
+                let ratio = contrast_ratio(foreground, background);
+                if ratio < min_ratio {
+                    diagnostics.push(Diagnostic::LowContrast {
+                        tags: stack.join(" "),
+                        foreground,
+                        background,
+                        ratio: (ratio * 100.0) as u32,
+                    });
+                }
+            }
+        }
+        diagnostics
+    }
+
+    /// Lighten or darken the foreground of every tag with a rule until its contrast ratio with
+    /// the tag's background, or the palette's, is at least `min_ratio`. Both colours are the
+    /// tag's cascaded ones, with rules in `@media` queries matched against the default
+    /// [`RenderContext`].
+    ///
+    /// Only the declaration that sets the tag's foreground is changed, to a true colour. If it
+    /// referred to a variable, e.g. `foreground: var(--accent)`, it no longer does; the variable
+    /// keeps its value. Tags that are reversed, hidden or coloured by a gradient are skipped, as
+    /// are tags whose foreground is the palette's or set by a keyword such as `inherit`.
+    /// Combinations only found by nesting tags are not adjusted, so check the result with
+    /// [`StyleSheet::check_contrast`].
+    pub fn ensure_contrast(&mut self, min_ratio: f32) {
+        let context = RenderContext::default();
+        let tags: Vec<String> = self.rule_tags().into_iter().map(String::from).collect();
+        for tag in &tags {
+            let style = self.style(&[tag], &context);
+            if style.foreground.is_none() || style.styles.contains(&Styles::Reversed) {
+                continue;
+            }
+            let Some((foreground, background)) = self.drawn_colors(&style) else {
+                continue;
+            };
+            if contrast_ratio(foreground, background) >= min_ratio {
+                continue;
+            }
+            let Some((rule, declaration)) = self.foreground_declaration(tag, &context) else {
+                continue;
+            };
+            let (r, g, b) = nudge(foreground, background, min_ratio);
+            self.rules[rule].declarations[declaration].property =
+                Property::Foreground(ColorExpr::Color(Color::TrueColor { r, g, b }));
+            // Later tags cascade from the adjusted rule.
+            self.resolve()
+                .expect("Stylesheet was resolvable before adjusting colours");
+        }
+    }
+}
+
+#[test]
+fn test_contrast() {
+    use crate::palette::Palette;
+
+    let mut style_sheet = StyleSheet::parse(
+        "
+        error { foreground: #ff5555 }
+        hint { foreground: #cccccc }
+        badge { foreground: black; background: yellow }
+        warn { foreground: yellow }
+        ",
+    )
+    .unwrap();
+    let context = RenderContext::default();
+    let messages = |style_sheet: &StyleSheet| -> Vec<String> {
+        let diagnostics = style_sheet.check_contrast(4.5, &context);
+        diagnostics.iter().map(|d| d.to_string()).collect()
+    };
+    // On black, only text nested in a yellow badge is hard to read.
+    assert_eq!(
+        messages(&style_sheet),
+        vec![
+            "badge error is #ff5555 on #cdcd00, a contrast ratio of 1.8:1",
+            "badge hint is #cccccc on #cdcd00, a contrast ratio of 1.0:1",
+            "badge warn is #cdcd00 on #cdcd00, a contrast ratio of 1.0:1",
+        ]
+    );
+    assert_eq!(
+        style_sheet.check_contrast(4.5, &context)[0],
+        Diagnostic::LowContrast {
+            tags: "badge error".to_string(),
+            foreground: (255, 85, 85),
+            background: (205, 205, 0),
+            ratio: 184,
+        }
+    );
+
+    let mut light = Palette::default();
+    light.set_background((255, 255, 255));
+    light.set_foreground((0, 0, 0));
+    style_sheet.set_palette(light);
+    assert_eq!(
+        messages(&style_sheet),
+        vec![
+            "error is #ff5555 on #ffffff, a contrast ratio of 3.1:1",
+            "badge error is #ff5555 on #cdcd00, a contrast ratio of 1.8:1",
+            "hint is #cccccc on #ffffff, a contrast ratio of 1.6:1",
+            "badge hint is #cccccc on #cdcd00, a contrast ratio of 1.0:1",
+            "warn is #cdcd00 on #ffffff, a contrast ratio of 1.7:1",
+            "badge warn is #cdcd00 on #cdcd00, a contrast ratio of 1.0:1",
+        ]
+    );
+
+    // Colours are darkened just enough for the white background, but not for the badge.
+    style_sheet.ensure_contrast(4.5);
+    assert_eq!(
+        messages(&style_sheet),
+        vec![
+            "badge error is #ee0000 on #cdcd00, a contrast ratio of 2.6:1",
+            "badge hint is #757575 on #cdcd00, a contrast ratio of 2.7:1",
+            "badge warn is #767600 on #cdcd00, a contrast ratio of 2.8:1",
+        ]
+    );
+    let error = style_sheet.style(&["error"], &context).foreground.unwrap();
+    assert!(contrast_ratio(error.rgb(), (255, 255, 255)) >= 4.5);
+    assert!(contrast_ratio(error.rgb(), (255, 255, 255)) < 5.0);
+
+    // The background from a second rule counts, and only the winning declaration changes.
+    let mut style_sheet = StyleSheet::parse(
+        "
+        :root { --dim: #333333 }
+        error { foreground: red }
+        error { background: #800000 }
+        note { foreground: var(--dim) !important; foreground: #444444 }
+        hint { foreground: var(--dim) }
+        ",
+    )
+    .unwrap();
+    // Tags nested in the dark red error are not adjusted for it, so only tags on their own.
+    let alone = |style_sheet: &StyleSheet| -> Vec<String> {
+        let messages = messages(style_sheet).into_iter();
+        let nested = |message: &String| message.split(" is ").next().unwrap().contains(' ');
+        messages.filter(|message| !nested(message)).collect()
+    };
+    assert_eq!(
+        alone(&style_sheet),
+        vec![
+            "error is #cd0000 on #800000, a contrast ratio of 1.8:1",
+            "note is #333333 on #000000, a contrast ratio of 1.6:1",
+            "hint is #333333 on #000000, a contrast ratio of 1.6:1",
+        ]
+    );
+    style_sheet.ensure_contrast(4.5);
+    assert_eq!(alone(&style_sheet), Vec::<String>::new());
+    assert_eq!(
+        style_sheet.to_css(),
+        "\
+:root {
+    --dim: #333333;
+}
+
+error {
+    foreground: #ff8080;
+}
+
+error {
+    background: #800000;
+}
+
+note {
+    foreground: #757575 !important;
+    foreground: #444444;
+}
+
+hint {
+    foreground: #757575;
+}"
+    );
+}
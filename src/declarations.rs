@@ -0,0 +1,668 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::palette::*;
+use crate::styles::*;
+
+/// A colour as written in a stylesheet, before variables are substituted.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum ColorExpr {
+    Color(Color),
+    /// `var(--name)` or `var(--name, fallback)`.
+    Var(String, Option<Box<ColorExpr>>),
+    /// A function of a colour and an amount, e.g. `lighten(red, 20%)`.
+    Adjust(Adjustment, Box<ColorExpr>, f32),
+    /// `mix(first, second, 30%)`, where the amount is the proportion of the first colour.
+    Mix(Box<ColorExpr>, Box<ColorExpr>, f32),
+    /// `palette(red)` or `base08`: the palette's RGB value for a name [`Palette::get`] knows.
+    Palette(String),
+}
+
+/// `linear-gradient(red, blue)` or `rainbow`, before variables are substituted.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum GradientExpr {
+    Linear(Vec<ColorExpr>),
+    Rainbow,
+}
+
+impl GradientExpr {
+    fn resolve(
+        &self,
+        variables: &HashMap<String, ColorExpr>,
+        palette: &Palette,
+    ) -> Result<Gradient> {
+        Ok(match self {
+            GradientExpr::Linear(stops) => Gradient::Linear(
+                stops
+                    .iter()
+                    .map(|stop| Ok(palette.rgb(stop.resolve(variables, palette)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            GradientExpr::Rainbow => Gradient::Rainbow,
+        })
+    }
+}
+
+/// The colour functions that take a colour and an amount.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Adjustment {
+    /// Add to the lightness.
+    Lighten,
+    /// Subtract from the lightness.
+    Darken,
+    /// Add to the saturation.
+    Saturate,
+    /// Subtract from the saturation.
+    Desaturate,
+    /// Set the opacity, blending with the palette's background.
+    Alpha,
+}
+
+impl Adjustment {
+    fn apply(&self, rgb: (u8, u8, u8), amount: f32, palette: &Palette) -> (u8, u8, u8) {
+        let (hue, saturation, lightness) = to_hsl(rgb);
+        let hsl = match self {
+            Adjustment::Lighten => (hue, saturation, lightness + amount),
+            Adjustment::Darken => (hue, saturation, lightness - amount),
+            Adjustment::Saturate => (hue, saturation + amount, lightness),
+            Adjustment::Desaturate => (hue, saturation - amount, lightness),
+            Adjustment::Alpha => return mix(rgb, palette.background(), amount),
+        };
+        from_hsl((hsl.0, hsl.1.clamp(0.0, 1.0), hsl.2.clamp(0.0, 1.0)))
+    }
+}
+
+/// Variables are nested at most this deep before we assume a cycle.
+const MAX_VARIABLE_DEPTH: usize = 32;
+
+impl ColorExpr {
+    /// The colour, with variables substituted and colour functions computed using `palette`.
+    pub(crate) fn resolve(
+        &self,
+        variables: &HashMap<String, ColorExpr>,
+        palette: &Palette,
+    ) -> Result<Color> {
+        self.resolve_at_depth(variables, palette, 0)
+    }
+
+    fn resolve_at_depth(
+        &self,
+        variables: &HashMap<String, ColorExpr>,
+        palette: &Palette,
+        depth: usize,
+    ) -> Result<Color> {
+        if depth > MAX_VARIABLE_DEPTH {
+            return Err(anyhow!("Variable cycle detected"));
+        }
+        let resolve = |expr: &ColorExpr| expr.resolve_at_depth(variables, palette, depth + 1);
+        let true_color = |(r, g, b)| Color::TrueColor { r, g, b };
+        match self {
+            ColorExpr::Color(color) => Ok(*color),
+            ColorExpr::Var(name, fallback) => match (variables.get(name), fallback) {
+                (Some(value), _) => resolve(value),
+                (None, Some(fallback)) => resolve(fallback),
+                (None, None) => Err(anyhow!("Undefined variable --{}", name)),
+            },
+            ColorExpr::Adjust(adjustment, color, amount) => {
+                let rgb = palette.rgb(resolve(color)?);
+                Ok(true_color(adjustment.apply(rgb, *amount, palette)))
+            }
+            ColorExpr::Mix(first, second, weight) => {
+                let (first, second) = (palette.rgb(resolve(first)?), palette.rgb(resolve(second)?));
+                Ok(true_color(mix(first, second, *weight)))
+            }
+            ColorExpr::Palette(name) => palette
+                .get(name)
+                .map(true_color)
+                .ok_or_else(|| anyhow!("Unknown palette colour {}", name)),
+        }
+    }
+}
+
+#[test]
+fn test_resolve_color_expr() {
+    let mut variables = HashMap::new();
+    variables.insert("accent".to_string(), ColorExpr::Color(Color::Red));
+    variables.insert(
+        "alias".to_string(),
+        ColorExpr::Var("accent".to_string(), None),
+    );
+    variables.insert("loop".to_string(), ColorExpr::Var("loop".to_string(), None));
+    let palette = Palette::default();
+
+    let var = |name: &str, fallback: Option<ColorExpr>| {
+        ColorExpr::Var(name.to_string(), fallback.map(Box::new))
+    };
+    let resolve = |expr: ColorExpr| expr.resolve(&variables, &palette);
+    assert_eq!(resolve(var("accent", None)).unwrap(), Color::Red);
+    assert_eq!(resolve(var("alias", None)).unwrap(), Color::Red);
+    assert_eq!(
+        resolve(var("missing", Some(ColorExpr::Color(Color::Blue)))).unwrap(),
+        Color::Blue
+    );
+    assert!(resolve(var("missing", None)).is_err());
+    assert!(resolve(var("loop", None)).is_err());
+}
+
+#[test]
+fn test_color_functions() {
+    let variables = HashMap::new();
+    let mut palette = Palette::default();
+    let color = |color| Box::new(ColorExpr::Color(color));
+    let resolve = |expr: ColorExpr, palette: &Palette| expr.resolve(&variables, palette).unwrap();
+    let true_color = |r, g, b| Color::TrueColor { r, g, b };
+
+    let lighten = ColorExpr::Adjust(Adjustment::Lighten, color(Color::Red), 0.2);
+    assert_eq!(resolve(lighten.clone(), &palette), true_color(255, 52, 52));
+    palette.set(Color::Red, (255, 0, 0));
+    assert_eq!(resolve(lighten, &palette), true_color(255, 102, 102));
+    let darken = ColorExpr::Adjust(Adjustment::Darken, color(Color::White), 1.0);
+    assert_eq!(resolve(darken, &palette), true_color(0, 0, 0));
+    let desaturate = ColorExpr::Adjust(Adjustment::Desaturate, color(Color::Red), 1.0);
+    assert_eq!(resolve(desaturate, &palette), true_color(128, 128, 128));
+    let alpha = ColorExpr::Adjust(Adjustment::Alpha, color(Color::Red), 0.5);
+    assert_eq!(resolve(alpha, &palette), true_color(128, 0, 0));
+    let mix = ColorExpr::Mix(color(Color::Red), color(Color::Blue), 0.3);
+    assert_eq!(resolve(mix, &palette), true_color(77, 0, 167));
+    let base08 = ColorExpr::Palette("base08".to_string());
+    assert_eq!(resolve(base08, &palette), true_color(255, 0, 0));
+}
+
+/// An amount as the shortest percentage that parses back to it.
+fn percentage(amount: f32) -> String {
+    let percentage = amount as f64 * 100.0;
+    (0..=9)
+        .map(|precision| format!("{:.*}", precision, percentage))
+        .find(|written| {
+            written
+                .parse::<f32>()
+                .is_ok_and(|parsed| parsed / 100.0 == amount)
+        })
+        .unwrap_or_else(|| percentage.to_string())
+}
+
+impl fmt::Display for ColorExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorExpr::Color(color) => write!(f, "{}", color),
+            ColorExpr::Var(name, None) => write!(f, "var(--{})", name),
+            ColorExpr::Var(name, Some(fallback)) => write!(f, "var(--{}, {})", name, fallback),
+            ColorExpr::Adjust(adjustment, color, amount) => {
+                let name = match adjustment {
+                    Adjustment::Lighten => "lighten",
+                    Adjustment::Darken => "darken",
+                    Adjustment::Saturate => "saturate",
+                    Adjustment::Desaturate => "desaturate",
+                    Adjustment::Alpha => "alpha",
+                };
+                write!(f, "{}({}, {}%)", name, color, percentage(*amount))
+            }
+            ColorExpr::Mix(first, second, weight) => {
+                write!(f, "mix({}, {}, {}%)", first, second, percentage(*weight))
+            }
+            ColorExpr::Palette(name) => write!(f, "palette({})", name),
+        }
+    }
+}
+
+/// A property and its value, before variables are substituted.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) enum Property {
+    Foreground(ColorExpr),
+    /// `foreground: linear-gradient(red, blue)` or `foreground: rainbow`.
+    Gradient(GradientExpr),
+    Background(ColorExpr),
+    Styles(Vec<Styles>),
+    /// Styles to remove from those set by enclosing tags, e.g. `-bold` or `no-underline`.
+    RemoveStyles(Vec<Styles>),
+    /// A CSS-wide keyword in place of a value, e.g. `foreground: inherit` or `styles: none`.
+    Keyword(PropertyName, Keyword),
+    /// `content: "text" / "ascii fallback"`.
+    Content(Content),
+    TextTransform(TextTransform),
+    UnderlineStyle(UnderlineStyle),
+    UnderlineColor(ColorExpr),
+    /// `sgr: "38;5;208;53"`: raw SGR parameters for anything without a property.
+    Sgr(String),
+    Width(usize),
+    TextAlign(TextAlign),
+    Overflow(Overflow),
+    PaddingLeft(usize),
+    PaddingRight(usize),
+}
+
+/// The properties that accept CSS-wide keywords.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PropertyName {
+    Foreground,
+    Background,
+    Styles,
+}
+
+impl Property {
+    pub(crate) fn resolve(
+        &self,
+        variables: &HashMap<String, ColorExpr>,
+        palette: &Palette,
+    ) -> Result<Style> {
+        Ok(match self {
+            Property::Foreground(color) => {
+                Style::new(None, Some(color.resolve(variables, palette)?), None)
+            }
+            Property::Background(color) => {
+                Style::new(None, None, Some(color.resolve(variables, palette)?))
+            }
+            Property::Styles(styles) => Style::new(Some(styles.clone()), None, None),
+            Property::RemoveStyles(styles) => Style {
+                removed_styles: styles.clone(),
+                ..Default::default()
+            },
+            Property::UnderlineStyle(underline_style) => Style {
+                underline_style: Some(*underline_style),
+                ..Default::default()
+            },
+            Property::UnderlineColor(color) => Style {
+                underline_color: Some(color.resolve(variables, palette)?),
+                ..Default::default()
+            },
+            Property::Gradient(gradient) => Style {
+                gradient: Some(gradient.resolve(variables, palette)?),
+                ..Default::default()
+            },
+            Property::Sgr(sgr) => Style {
+                sgr: Some(sgr.clone()),
+                ..Default::default()
+            },
+            Property::Width(width) => Style {
+                width: Some(*width),
+                ..Default::default()
+            },
+            Property::TextAlign(text_align) => Style {
+                text_align: Some(*text_align),
+                ..Default::default()
+            },
+            Property::Overflow(overflow) => Style {
+                overflow: Some(*overflow),
+                ..Default::default()
+            },
+            Property::PaddingLeft(padding) => Style {
+                padding_left: Some(*padding),
+                ..Default::default()
+            },
+            Property::PaddingRight(padding) => Style {
+                padding_right: Some(*padding),
+                ..Default::default()
+            },
+            Property::TextTransform(text_transform) => Style {
+                text_transform: Some(*text_transform),
+                ..Default::default()
+            },
+            Property::Content(content) => Style {
+                content: Some(content.clone()),
+                ..Default::default()
+            },
+            Property::Keyword(name, keyword) => {
+                let keyword = Some(*keyword);
+                match name {
+                    PropertyName::Foreground => Style {
+                        foreground_keyword: keyword,
+                        ..Default::default()
+                    },
+                    PropertyName::Background => Style {
+                        background_keyword: keyword,
+                        ..Default::default()
+                    },
+                    PropertyName::Styles => Style {
+                        styles_keyword: keyword,
+                        ..Default::default()
+                    },
+                }
+            }
+        })
+    }
+}
+
+/// A string in double quotes, escaped as the stylesheet parser expects.
+fn quote(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+fn join<T: fmt::Display>(items: &[T], prefix: &str, separator: &str) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| format!("{}{}", prefix, item))
+        .collect();
+    items.join(separator)
+}
+
+impl Property {
+    /// The name the property is written with.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Property::Foreground(_) | Property::Gradient(_) => "foreground",
+            Property::Background(_) => "background",
+            Property::Styles(_) | Property::RemoveStyles(_) => "styles",
+            Property::Keyword(PropertyName::Foreground, _) => "foreground",
+            Property::Keyword(PropertyName::Background, _) => "background",
+            Property::Keyword(PropertyName::Styles, _) => "styles",
+            Property::Content(_) => "content",
+            Property::TextTransform(_) => "text-transform",
+            Property::UnderlineStyle(_) => "underline-style",
+            Property::UnderlineColor(_) => "underline-color",
+            Property::Sgr(_) => "sgr",
+            Property::Width(_) => "width",
+            Property::TextAlign(_) => "text-align",
+            Property::Overflow(_) => "overflow",
+            Property::PaddingLeft(_) => "padding-left",
+            Property::PaddingRight(_) => "padding-right",
+        }
+    }
+
+    /// The property's value in canonical stylesheet syntax, e.g. `red`.
+    pub(crate) fn value(&self) -> String {
+        match self {
+            Property::Foreground(color)
+            | Property::Background(color)
+            | Property::UnderlineColor(color) => color.to_string(),
+            Property::Gradient(GradientExpr::Linear(stops)) => {
+                format!("linear-gradient({})", join(stops, "", ", "))
+            }
+            Property::Gradient(GradientExpr::Rainbow) => "rainbow".to_string(),
+            Property::Styles(styles) => join(styles, "", " "),
+            Property::RemoveStyles(styles) => join(styles, "-", " "),
+            Property::Keyword(_, Keyword::Inherit) => "inherit".to_string(),
+            Property::Keyword(_, Keyword::Initial) => "initial".to_string(),
+            Property::Content(Content { text, ascii }) => match ascii {
+                Some(ascii) => format!("{} / {}", quote(text), quote(ascii)),
+                None => quote(text),
+            },
+            Property::TextTransform(text_transform) => match text_transform {
+                TextTransform::None => "none",
+                TextTransform::Uppercase => "uppercase",
+                TextTransform::Lowercase => "lowercase",
+                TextTransform::Capitalize => "capitalize",
+            }
+            .to_string(),
+            Property::UnderlineStyle(underline_style) => match underline_style {
+                UnderlineStyle::Single => "single",
+                UnderlineStyle::Double => "double",
+                UnderlineStyle::Curly => "curly",
+                UnderlineStyle::Dotted => "dotted",
+                UnderlineStyle::Dashed => "dashed",
+            }
+            .to_string(),
+            Property::Sgr(sgr) => quote(sgr),
+            Property::Width(width) => width.to_string(),
+            Property::TextAlign(text_align) => match text_align {
+                TextAlign::Left => "left",
+                TextAlign::Right => "right",
+                TextAlign::Center => "center",
+            }
+            .to_string(),
+            Property::Overflow(overflow) => match overflow {
+                Overflow::Visible => "visible",
+                Overflow::Clip => "clip",
+                Overflow::Ellipsis => "ellipsis",
+            }
+            .to_string(),
+            Property::PaddingLeft(padding) | Property::PaddingRight(padding) => padding.to_string(),
+        }
+    }
+}
+
+/// The property in canonical stylesheet syntax, e.g. `foreground: red`.
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name(), self.value())
+    }
+}
+
+/// A single stylesheet declaration, such as `foreground: red !important`.
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Declaration {
+    pub(crate) property: Property,
+    /// Important declarations take precedence over all normal declarations in the cascade.
+    pub(crate) important: bool,
+}
+
+impl From<Property> for Declaration {
+    fn from(property: Property) -> Declaration {
+        Declaration {
+            property,
+            important: false,
+        }
+    }
+}
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.property)?;
+        if self.important {
+            write!(f, " !important")?;
+        }
+        Ok(())
+    }
+}
+
+impl Declaration {
+    /// The declarations equivalent to an already resolved `Style`.
+    pub(crate) fn from_style(style: &Style) -> Vec<Declaration> {
+        let mut properties = Vec::new();
+        if let Some(keyword) = style.styles_keyword {
+            properties.push(Property::Keyword(PropertyName::Styles, keyword));
+        }
+        if !style.styles.is_empty() {
+            properties.push(Property::Styles(style.styles.clone()));
+        }
+        if !style.removed_styles.is_empty() {
+            properties.push(Property::RemoveStyles(style.removed_styles.clone()));
+        }
+        if let Some(keyword) = style.foreground_keyword {
+            properties.push(Property::Keyword(PropertyName::Foreground, keyword));
+        }
+        if let Some(color) = style.foreground {
+            properties.push(Property::Foreground(ColorExpr::Color(color)));
+        }
+        match &style.gradient {
+            Some(Gradient::Linear(stops)) => {
+                let stops = stops
+                    .iter()
+                    .map(|&(r, g, b)| ColorExpr::Color(Color::TrueColor { r, g, b }));
+                properties.push(Property::Gradient(GradientExpr::Linear(stops.collect())));
+            }
+            Some(Gradient::Rainbow) => properties.push(Property::Gradient(GradientExpr::Rainbow)),
+            None => {}
+        }
+        if let Some(keyword) = style.background_keyword {
+            properties.push(Property::Keyword(PropertyName::Background, keyword));
+        }
+        if let Some(color) = style.background {
+            properties.push(Property::Background(ColorExpr::Color(color)));
+        }
+        if let Some(content) = &style.content {
+            properties.push(Property::Content(content.clone()));
+        }
+        if let Some(text_transform) = style.text_transform {
+            properties.push(Property::TextTransform(text_transform));
+        }
+        if let Some(underline_style) = style.underline_style {
+            properties.push(Property::UnderlineStyle(underline_style));
+        }
+        if let Some(color) = style.underline_color {
+            properties.push(Property::UnderlineColor(ColorExpr::Color(color)));
+        }
+        if let Some(sgr) = &style.sgr {
+            properties.push(Property::Sgr(sgr.clone()));
+        }
+        if let Some(width) = style.width {
+            properties.push(Property::Width(width));
+        }
+        if let Some(text_align) = style.text_align {
+            properties.push(Property::TextAlign(text_align));
+        }
+        if let Some(overflow) = style.overflow {
+            properties.push(Property::Overflow(overflow));
+        }
+        if let Some(padding) = style.padding_left {
+            properties.push(Property::PaddingLeft(padding));
+        }
+        if let Some(padding) = style.padding_right {
+            properties.push(Property::PaddingRight(padding));
+        }
+        properties.into_iter().map(Declaration::from).collect()
+    }
+}
+
+impl Declaration {
+    /// The declarations equivalent to SGR parameters such as `1;38;5;208`, as found in
+    /// `LS_COLORS`. Parameters without an equivalent property are kept in an `sgr` declaration,
+    /// and `0` is ignored.
+    pub(crate) fn from_sgr(sgr: &str) -> Result<Vec<Declaration>> {
+        let mut styles = Vec::new();
+        // The foreground, background and underline colours.
+        let mut colors = [None, None, None];
+        let mut underline_style = None;
+        let mut raw = Vec::new();
+        let mut parameters = sgr.split(';').filter(|parameter| !parameter.is_empty());
+        while let Some(parameter) = parameters.next() {
+            if let Some(style) = UnderlineStyle::ALL
+                .into_iter()
+                .find(|style| style.sgr() == parameter)
+            {
+                underline_style = Some(style);
+                continue;
+            }
+            if parameter.contains(':') {
+                raw.push(parameter.to_string());
+                continue;
+            }
+            let code: u8 = parameter
+                .parse()
+                .map_err(|_| anyhow!("Invalid SGR parameter {:?} in {:?}", parameter, sgr))?;
+            let named = |base: u8| Color::NAMED[(code - base) as usize];
+            match code {
+                0 => {}
+                30..=37 => colors[0] = Some(named(30)),
+                90..=97 => colors[0] = Some(named(82)),
+                40..=47 => colors[1] = Some(named(40)),
+                100..=107 => colors[1] = Some(named(92)),
+                38 | 48 | 58 => {
+                    // The colour space, then as many values as it takes: an index for `5`,
+                    // RGB for `2`, CMY for `3`, CMYK for `4` and none for transparent `1`.
+                    let space = parameters.next();
+                    let count = match space {
+                        Some("5") => 1,
+                        Some("2" | "3") => 3,
+                        Some("4") => 4,
+                        Some("1") => 0,
+                        _ => return Err(anyhow!("Invalid extended colour in {:?}", sgr)),
+                    };
+                    let rest: Vec<&str> = parameters.by_ref().take(count).collect();
+                    let values = rest
+                        .iter()
+                        .map(|value| value.parse::<u8>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .ok()
+                        .filter(|values| values.len() == count)
+                        .ok_or_else(|| anyhow!("Invalid extended colour in {:?}", sgr))?;
+                    let color = match (space, &values[..]) {
+                        (Some("5"), &[index]) if index < 16 => Color::NAMED[index as usize],
+                        (Some("2"), &[r, g, b]) => Color::TrueColor { r, g, b },
+                        // Kept whole, so its values are not read as codes of their own.
+                        _ => {
+                            let code = code.to_string();
+                            let parameters = [code.as_str()].into_iter().chain(space).chain(rest);
+                            raw.push(parameters.collect::<Vec<_>>().join(";"));
+                            continue;
+                        }
+                    };
+                    colors[((code - 38) / 10) as usize] = Some(color);
+                }
+                _ => match Styles::ALL.into_iter().find(|style| style.sgr() == code) {
+                    Some(style) => styles.push(style),
+                    None => raw.push(code.to_string()),
+                },
+            }
+        }
+        let mut properties = Vec::new();
+        if !styles.is_empty() {
+            properties.push(Property::Styles(styles));
+        }
+        if let Some(color) = colors[0] {
+            properties.push(Property::Foreground(ColorExpr::Color(color)));
+        }
+        if let Some(color) = colors[1] {
+            properties.push(Property::Background(ColorExpr::Color(color)));
+        }
+        if let Some(underline_style) = underline_style {
+            properties.push(Property::UnderlineStyle(underline_style));
+        }
+        if let Some(color) = colors[2] {
+            properties.push(Property::UnderlineColor(ColorExpr::Color(color)));
+        }
+        if !raw.is_empty() {
+            properties.push(Property::Sgr(raw.join(";")));
+        }
+        Ok(properties.into_iter().map(Declaration::from).collect())
+    }
+}
+
+#[test]
+fn test_from_sgr() {
+    let to_string = |sgr: &str| {
+        let declarations = Declaration::from_sgr(sgr).unwrap();
+        let declarations: Vec<String> = declarations.iter().map(|d| d.to_string()).collect();
+        declarations.join("; ")
+    };
+    assert_eq!(to_string("01;31"), "styles: bold; foreground: red");
+    assert_eq!(
+        to_string("4;96;104"),
+        "styles: underline; foreground: bright-cyan; background: bright-blue"
+    );
+    assert_eq!(
+        to_string("38;5;9;48;2;255;136;0;4:3"),
+        "foreground: bright-red; background: #ff8800; underline-style: curly"
+    );
+    assert_eq!(
+        to_string("38;5;208;1;60;0"),
+        "styles: bold; sgr: \"38;5;208;60\""
+    );
+    assert_eq!(
+        to_string("58;5;196;4"),
+        "styles: underline; sgr: \"58;5;196\""
+    );
+    assert_eq!(to_string("58;2;255;0;0;58;5;3"), "underline-color: yellow");
+    assert_eq!(to_string("58;2;1;2;3"), "underline-color: #010203");
+    assert_eq!(
+        to_string("38;4;0;1;2;3;1;48;1"),
+        "styles: bold; sgr: \"38;4;0;1;2;3;48;1\""
+    );
+    assert_eq!(to_string("53;14"), "styles: overline font-4");
+    assert_eq!(to_string(""), "");
+    assert!(Declaration::from_sgr("1;x").is_err());
+    assert!(Declaration::from_sgr("38;5").is_err());
+    assert!(Declaration::from_sgr("58;2;1;2").is_err());
+    assert!(Declaration::from_sgr("58;9;1").is_err());
+}
+
+/// Resolve the normal (or important) declarations in a list into a single `Style`, later
+/// declarations taking precedence.
+pub(crate) fn resolve_declarations(
+    declarations: &[Declaration],
+    important: bool,
+    variables: &HashMap<String, ColorExpr>,
+    palette: &Palette,
+) -> Result<Style> {
+    let mut result = Style::default();
+    for declaration in declarations {
+        if declaration.important == important {
+            result = result.merge(declaration.property.resolve(variables, palette)?);
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,308 @@
+//! Stylesheets from `LS_COLORS` and `dircolors` databases, to colour file names as `ls` does.
+use anyhow::{anyhow, Context, Result};
+use std::fs::Metadata;
+use std::path::Path;
+
+use crate::stylesheet::parse_assignments;
+use crate::StyleSheet;
+
+/// The `dircolors` keywords and the `LS_COLORS` keys they stand for.
+const KEYWORDS: [(&str, &str); 32] = [
+    ("NORMAL", "no"),
+    ("NORM", "no"),
+    ("FILE", "fi"),
+    ("RESET", "rs"),
+    ("DIR", "di"),
+    ("LNK", "ln"),
+    ("LINK", "ln"),
+    ("SYMLINK", "ln"),
+    ("MULTIHARDLINK", "mh"),
+    ("FIFO", "pi"),
+    ("PIPE", "pi"),
+    ("SOCK", "so"),
+    ("DOOR", "do"),
+    ("BLK", "bd"),
+    ("BLOCK", "bd"),
+    ("CHR", "cd"),
+    ("CHAR", "cd"),
+    ("ORPHAN", "or"),
+    ("MISSING", "mi"),
+    ("SETUID", "su"),
+    ("SETGID", "sg"),
+    ("CAPABILITY", "ca"),
+    ("STICKY_OTHER_WRITABLE", "tw"),
+    ("OTHER_WRITABLE", "ow"),
+    ("STICKY", "st"),
+    ("EXEC", "ex"),
+    ("LEFTCODE", "lc"),
+    ("LEFT", "lc"),
+    ("RIGHTCODE", "rc"),
+    ("RIGHT", "rc"),
+    ("ENDCODE", "ec"),
+    ("END", "ec"),
+];
+
+/// The keys for the escape codes around each file name, which do not style anything.
+const CODES: [&str; 4] = ["lc", "rc", "ec", "rs"];
+
+/// The selector for an extension, e.g. `ext-tar-gz` for `tar.gz`, if it can be written as one.
+fn extension_selector(extension: &str) -> Option<String> {
+    let extension = extension.to_lowercase().replace('.', "-");
+    let valid = !extension.is_empty()
+        && extension
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| format!("ext-{}", extension))
+}
+
+/// The selector for an `LS_COLORS` key, or `None` if it has none.
+fn selector(key: &str) -> Option<String> {
+    match key.strip_prefix("*.") {
+        Some(extension) => extension_selector(extension),
+        None if key.starts_with('*') || CODES.contains(&key) => None,
+        None => Some(key.to_string()),
+    }
+}
+
+impl StyleSheet<'static> {
+    /// A stylesheet from the contents of an `LS_COLORS` variable, e.g. `di=01;34:*.rs=0;33`.
+    ///
+    /// File types become selectors named after their keys (`di`, `ln`, `ex`, ...) and
+    /// extensions become `ext-` selectors, lowercased and with dots replaced by dashes: `*.rs`
+    /// is `ext-rs` and `*.tar.gz` is `ext-tar-gz`. Other globs, such as `*~`, and the codes
+    /// written around each file name (`lc`, `rc`, `ec` and `rs`) are ignored, as is
+    /// `ln=target`. Use [`StyleSheet::file_selector`] to choose the selector for a file.
+    pub fn from_ls_colors(ls_colors: &str) -> Result<StyleSheet<'static>> {
+        let rules = parse_assignments(ls_colors)?
+            .into_iter()
+            .filter(|(_, sgr)| *sgr != "target")
+            .filter_map(|(key, sgr)| Some((selector(key)?, sgr)));
+        StyleSheet::from_sgr(rules)
+    }
+
+    /// A stylesheet from a `dircolors` database, as printed by `dircolors --print-database`.
+    /// Entries are used whatever the `TERM` and `COLORTERM` lines, and are converted as by
+    /// [`StyleSheet::from_ls_colors`].
+    pub fn from_dircolors(database: &str) -> Result<StyleSheet<'static>> {
+        let mut ls_colors = Vec::new();
+        for (index, line) in database.lines().enumerate() {
+            let line = match line.find(" #").or(line.find("\t#")) {
+                Some(comment) => &line[..comment],
+                None => line,
+            };
+            let mut words = line.split_whitespace();
+            let (Some(keyword), value) = (words.next(), words.next()) else {
+                continue;
+            };
+            if keyword.starts_with('#') || ["TERM", "COLORTERM", "OPTIONS"].contains(&keyword) {
+                continue;
+            }
+            let value = value.ok_or_else(|| anyhow!("Missing value on line {}", index + 1))?;
+            let key = if keyword.starts_with('.') {
+                format!("*{}", keyword)
+            } else if keyword.starts_with('*') {
+                keyword.to_string()
+            } else {
+                let (_, key) = KEYWORDS
+                    .iter()
+                    .find(|(name, _)| keyword.eq_ignore_ascii_case(name))
+                    .ok_or_else(|| anyhow!("Unknown keyword {} on line {}", keyword, index + 1))?;
+                key.to_string()
+            };
+            ls_colors.push(format!("{}={}", key, value));
+        }
+        StyleSheet::from_ls_colors(&ls_colors.join(":")).context("Invalid dircolors database")
+    }
+}
+
+impl StyleSheet<'_> {
+    fn has_rule(&self, selector: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.selector.to_string() == selector)
+    }
+
+    /// The selector `ls` would colour a file with, given the file's path and its metadata from
+    /// [`std::fs::symlink_metadata`], e.g. `di` for a directory or `ext-rs` for `main.rs`. As
+    /// with `ls`, the more specific types (such as `tw` for a sticky, world-writable directory)
+    /// are only chosen if the stylesheet has a rule for them, and extensions only apply to
+    /// regular files that are not executable. The result can be used as a tag:
+    ///
+    /// ```no_run
+    /// # use colored_markup::StyleSheet;
+    /// let style_sheet = StyleSheet::from_ls_colors("di=01;34:ex=01;32:*.rs=33").unwrap();
+    /// let path = std::path::Path::new("src");
+    /// let tag = style_sheet.file_selector(path, &path.symlink_metadata().unwrap());
+    /// println!("{}", style_sheet.render(&format!("<{tag}>src</{tag}>")).unwrap());
+    /// ```
+    pub fn file_selector(&self, path: &Path, metadata: &Metadata) -> String {
+        let colored = |key: &str| self.has_rule(key);
+        let file_type = metadata.file_type();
+        let key = if file_type.is_symlink() {
+            if path.metadata().is_err() && colored("or") {
+                "or"
+            } else {
+                "ln"
+            }
+        } else if file_type.is_dir() {
+            special_directory(metadata, colored).unwrap_or("di")
+        } else if file_type.is_file() {
+            if let Some(key) = special_file(metadata, colored) {
+                return key.to_string();
+            }
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_lowercase();
+            // The longest matching extension wins, e.g. `tar.gz` over `gz`.
+            let extensions = name.match_indices('.').map(|(index, _)| &name[index + 1..]);
+            for extension in extensions {
+                match extension_selector(extension) {
+                    Some(selector) if colored(&selector) => return selector,
+                    _ => {}
+                }
+            }
+            "fi"
+        } else {
+            special_type(metadata).unwrap_or("fi")
+        };
+        key.to_string()
+    }
+}
+
+#[cfg(unix)]
+fn special_directory(metadata: &Metadata, colored: impl Fn(&str) -> bool) -> Option<&'static str> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = metadata.permissions().mode();
+    let (sticky, other_writable) = (mode & 0o1000 != 0, mode & 0o002 != 0);
+    [
+        ("tw", sticky && other_writable),
+        ("ow", other_writable),
+        ("st", sticky),
+    ]
+    .into_iter()
+    .find(|&(key, applies)| applies && colored(key))
+    .map(|(key, _)| key)
+}
+
+#[cfg(not(unix))]
+fn special_directory(_: &Metadata, _: impl Fn(&str) -> bool) -> Option<&'static str> {
+    None
+}
+
+#[cfg(unix)]
+fn special_file(metadata: &Metadata, colored: impl Fn(&str) -> bool) -> Option<&'static str> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    let mode = metadata.permissions().mode();
+    [
+        ("su", mode & 0o4000 != 0),
+        ("sg", mode & 0o2000 != 0),
+        ("ex", mode & 0o111 != 0),
+        ("mh", metadata.nlink() > 1),
+    ]
+    .into_iter()
+    .find(|&(key, applies)| applies && colored(key))
+    .map(|(key, _)| key)
+}
+
+#[cfg(not(unix))]
+fn special_file(_: &Metadata, _: impl Fn(&str) -> bool) -> Option<&'static str> {
+    None
+}
+
+#[cfg(unix)]
+fn special_type(metadata: &Metadata) -> Option<&'static str> {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = metadata.file_type();
+    [
+        ("pi", file_type.is_fifo()),
+        ("so", file_type.is_socket()),
+        ("bd", file_type.is_block_device()),
+        ("cd", file_type.is_char_device()),
+    ]
+    .into_iter()
+    .find(|&(_, applies)| applies)
+    .map(|(key, _)| key)
+}
+
+#[cfg(not(unix))]
+fn special_type(_: &Metadata) -> Option<&'static str> {
+    None
+}
+
+#[test]
+fn test_from_ls_colors() {
+    use crate::context::RenderContext;
+    use crate::styles::*;
+
+    let style_sheet =
+        StyleSheet::from_ls_colors("rs=0:di=01;34:ln=target:*.rs=0;33:*.TAR.GZ=31:*~=90:").unwrap();
+    let context = RenderContext::default();
+    assert_eq!(
+        style_sheet.style(&["di"], &context),
+        Style::new(Some(vec![Styles::Bold]), Some(Color::Blue), None)
+    );
+    assert_eq!(
+        style_sheet.style(&["ext-tar-gz"], &context),
+        Style::new(None, Some(Color::Red), None)
+    );
+    assert_eq!(
+        style_sheet.to_css(),
+        "di {\n    styles: bold;\n    foreground: blue;\n}\n\next-rs {\n    foreground: yellow;\n}\n\next-tar-gz {\n    foreground: red;\n}"
+    );
+    assert!(StyleSheet::from_ls_colors("di=1;x").is_err());
+
+    let database = "
+        # Configuration file for dircolors
+        TERM xterm*
+        COLORTERM ?*
+        OPTIONS -F -T 0
+        DIR 01;34 # directories
+        EXEC 01;32
+        .rs 00;33
+        *.md 00;36
+        *~ 00;90
+        ";
+    assert_eq!(
+        StyleSheet::from_dircolors(database).unwrap(),
+        StyleSheet::from_ls_colors("di=01;34:ex=01;32:*.rs=00;33:*.md=00;36").unwrap()
+    );
+    assert!(StyleSheet::from_dircolors("COLOUR 01").is_err());
+    assert!(StyleSheet::from_dircolors("DIR").is_err());
+}
+
+#[test]
+fn test_file_selector() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    for name in ["main.rs", "archive.tar.gz", "notes.gz", "README"] {
+        std::fs::write(dir.join(name), "").unwrap();
+    }
+    let style_sheet =
+        StyleSheet::from_ls_colors("di=01;34:ln=01;36:or=31:ex=32:*.tar.gz=31:*.rs=33").unwrap();
+    let selector = |name: &str| {
+        let path = dir.join(name);
+        style_sheet.file_selector(&path, &path.symlink_metadata().unwrap())
+    };
+    assert_eq!(
+        style_sheet.file_selector(dir, &dir.metadata().unwrap()),
+        "di"
+    );
+    assert_eq!(selector("main.rs"), "ext-rs");
+    assert_eq!(selector("archive.tar.gz"), "ext-tar-gz");
+    assert_eq!(selector("notes.gz"), "fi");
+    assert_eq!(selector("README"), "fi");
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+        let permissions = std::fs::Permissions::from_mode(0o755);
+        std::fs::set_permissions(dir.join("main.rs"), permissions).unwrap();
+        assert_eq!(selector("main.rs"), "ex");
+        symlink(dir.join("README"), dir.join("link")).unwrap();
+        symlink(dir.join("missing"), dir.join("orphan")).unwrap();
+        assert_eq!(selector("link"), "ln");
+        assert_eq!(selector("orphan"), "or");
+    }
+}
@@ -0,0 +1,176 @@
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::context::RenderContext;
+use crate::palette::{ansi256, Palette};
+use crate::styles::*;
+
+/// Runs of text and the style each is rendered with.
+pub(crate) type Spans<'m> = Vec<(Cow<'m, str>, Style)>;
+
+/// The display width of the spans' text, in terminal columns.
+pub(crate) fn width(spans: &[(Cow<str>, Style)]) -> usize {
+    spans.iter().map(|(text, _)| text.width()).sum()
+}
+
+/// Apply a tag's `width`, `text-align`, `overflow` and `padding` to the spans of its content.
+/// `style` is the tag's own cascaded style, and `fill` the style for the spaces added.
+pub(crate) fn layout(spans: &mut Spans, style: &Style, fill: &Style, context: &RenderContext) {
+    let space = |count: usize| (Cow::Owned(" ".repeat(count)), fill.clone());
+    if let Some(target) = style.width {
+        let content_width = width(spans);
+        if content_width > target {
+            match style.overflow.unwrap_or(Overflow::Visible) {
+                Overflow::Visible => {}
+                Overflow::Clip => truncate(spans, target, ""),
+                Overflow::Ellipsis => {
+                    let ellipsis = if context.unicode { "…" } else { "..." };
+                    truncate(spans, target, ellipsis);
+                }
+            }
+        }
+        let slack = target.saturating_sub(width(spans));
+        let (left, right) = match style.text_align.unwrap_or(TextAlign::Left) {
+            TextAlign::Left => (0, slack),
+            TextAlign::Right => (slack, 0),
+            TextAlign::Center => (slack / 2, slack - slack / 2),
+        };
+        if left > 0 {
+            spans.insert(0, space(left));
+        }
+        if right > 0 {
+            spans.push(space(right));
+        }
+    }
+    if let Some(left) = style.padding_left.filter(|left| *left > 0) {
+        spans.insert(0, space(left));
+    }
+    if let Some(right) = style.padding_right.filter(|right| *right > 0) {
+        spans.push(space(right));
+    }
+}
+
+/// Colour each grapheme of the spans from a tag's `gradient`. Only spans still coloured by a
+/// gradient are the tag's own; the others were coloured by nested tags and keep their colour,
+/// though they still take up their place in the gradient. Each colour is the nearest that
+/// `context.colors` allows: a true colour, one of the 256 colours or a named colour in
+/// `palette`.
+pub(crate) fn apply_gradient(
+    spans: &mut Spans,
+    gradient: &Gradient,
+    context: &RenderContext,
+    palette: &Palette,
+) {
+    let count: usize = spans
+        .iter()
+        .map(|(text, _)| text.graphemes(true).count())
+        .sum();
+    let mut index = 0;
+    let mut result = Vec::with_capacity(count);
+    for (text, style) in spans.drain(..) {
+        if style.gradient.is_none() {
+            index += text.graphemes(true).count();
+            result.push((text, style));
+            continue;
+        }
+        for grapheme in text.graphemes(true) {
+            let position = index as f32 / count.saturating_sub(1).max(1) as f32;
+            let rgb = gradient.color_at(position);
+            let mut style = Style {
+                gradient: None,
+                ..style.clone()
+            };
+            if context.colors >= 1 << 24 {
+                let (r, g, b) = rgb;
+                style.foreground = Some(Color::TrueColor { r, g, b });
+            } else if context.colors >= 256 {
+                // There is no `Color` for the 256 colours, so they are written as raw SGR.
+                let code = format!("38;5;{}", ansi256(rgb));
+                style.sgr = Some(match style.sgr {
+                    Some(sgr) => format!("{};{}", code, sgr),
+                    None => code,
+                });
+            } else {
+                style.foreground = Some(palette.nearest(rgb));
+            }
+            result.push((Cow::Owned(grapheme.to_string()), style));
+            index += 1;
+        }
+    }
+    *spans = result;
+}
+
+/// Truncate the spans to `target` columns, including `ellipsis` at the end.
+fn truncate(spans: &mut Spans, target: usize, ellipsis: &str) {
+    let ellipsis = if ellipsis.width() > target {
+        ""
+    } else {
+        ellipsis
+    };
+    let limit = target - ellipsis.width();
+    let mut used = 0;
+    for index in 0..spans.len() {
+        let text = &spans[index].0;
+        let mut end = None;
+        for (offset, grapheme) in text.grapheme_indices(true) {
+            if used + grapheme.width() > limit {
+                end = Some(offset);
+                break;
+            }
+            used += grapheme.width();
+        }
+        if let Some(end) = end {
+            let mut text = text[..end].to_string();
+            text.push_str(ellipsis);
+            spans[index].0 = text.into();
+            spans.truncate(index + 1);
+            return;
+        }
+    }
+}
+
+#[test]
+fn test_layout() {
+    let spans = |texts: &[&'static str]| -> Spans {
+        texts
+            .iter()
+            .map(|text| (Cow::Borrowed(*text), Style::default()))
+            .collect()
+    };
+    let texts =
+        |spans: &Spans| -> Vec<String> { spans.iter().map(|(text, _)| text.to_string()).collect() };
+    let context = RenderContext::default();
+    let fill = Style::default();
+
+    let style = Style {
+        width: Some(6),
+        text_align: Some(TextAlign::Right),
+        ..Default::default()
+    };
+    let mut s = spans(&["ab", "c"]);
+    layout(&mut s, &style, &fill, &context);
+    assert_eq!(texts(&s), ["   ", "ab", "c"]);
+
+    let style = Style {
+        width: Some(4),
+        overflow: Some(Overflow::Ellipsis),
+        padding_left: Some(1),
+        padding_right: Some(1),
+        ..Default::default()
+    };
+    let mut s = spans(&["ab", "cdef"]);
+    layout(&mut s, &style, &fill, &context);
+    assert_eq!(texts(&s), [" ", "ab", "c…", " "]);
+
+    let style = Style {
+        width: Some(3),
+        overflow: Some(Overflow::Clip),
+        text_align: Some(TextAlign::Center),
+        ..Default::default()
+    };
+    // Wide characters are never split.
+    let mut s = spans(&["日本"]);
+    layout(&mut s, &style, &fill, &context);
+    assert_eq!(texts(&s), ["日", " "]);
+}
@@ -9,10 +9,24 @@
 //! }
 //! ```
 
+pub mod context;
+mod contrast;
+mod declarations;
+mod dircolors;
+mod layout;
+pub mod lint;
 mod markup;
+pub mod palette;
+mod palette_import;
+pub mod resolver;
+mod selector;
+#[cfg(feature = "serde")]
+mod serde_support;
 pub mod styles;
 pub mod stylesheet;
 mod stylesheet_parse;
+mod themes;
+pub mod watch;
 
 use styles::*;
 pub use stylesheet::*;
@@ -29,6 +43,7 @@ macro_rules! format_markup {
 
 #[test]
 fn test_format_markup() {
+    colored::control::set_override(true);
     let stylesheet = StyleSheet::parse("red { foreground: red }").unwrap();
     let result = format_markup!(&stylesheet, "Mode: <red>mode</red>");
     assert_eq!(result, "Mode: \u{1b}[31mmode\u{1b}[0m");
@@ -64,6 +79,7 @@ impl Styled for str {
 
 #[test]
 fn test_styled() {
+    colored::control::set_override(true);
     let stylesheet: StyleSheet<'_> = StyleSheet::parse("red { foreground: red }").unwrap();
     let result = "Mode: <red>mode</red>".styled(&stylesheet);
     assert_eq!(result, "Mode: \u{1b}[31mmode\u{1b}[0m");
@@ -77,6 +93,7 @@ impl Styled for String {
 
 #[test]
 fn test_styled_2() {
+    colored::control::set_override(true);
     let stylesheet: StyleSheet<'_> = StyleSheet::parse("red { foreground: red }").unwrap();
     let result = "Mode: <red>mode</red>".to_string().styled(&stylesheet);
     assert_eq!(result, "Mode: \u{1b}[31mmode\u{1b}[0m");
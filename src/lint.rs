@@ -0,0 +1,300 @@
+//! Checks for mistakes in stylesheets and for drift between stylesheets and the markup they
+//! style.
+use std::fmt;
+
+use crate::markup::{Markup, Part};
+use crate::stylesheet::Rule;
+use crate::StyleSheet;
+
+/// A problem found by [`StyleSheet::lint`].
+///
+/// Unknown properties and invalid values are not diagnostics: [`StyleSheet::parse`] rejects
+/// them with an error naming the declaration and its line.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Diagnostic {
+    /// A rule with the same selector and `@media` query as an earlier rule. Its declarations
+    /// are combined with the earlier rule's, which is easy to miss.
+    DuplicateSelector {
+        selector: String,
+        media: Option<String>,
+    },
+    /// A declaration that never takes effect, because a later declaration of the same property
+    /// for the same selector, or an `!important` one, always wins over it.
+    ShadowedDeclaration {
+        selector: String,
+        declaration: String,
+    },
+    /// A declaration that has no effect where it is, e.g. `content` outside `::before` and
+    /// `::after`.
+    NoOpDeclaration {
+        selector: String,
+        declaration: String,
+        reason: &'static str,
+    },
+    /// A rule without declarations.
+    EmptyRule { selector: String },
+    /// A rule for a tag that none of the templates use.
+    UnusedRule { selector: String },
+    /// A tag used in a template, numbered from 0, that no rule styles.
+    UndefinedTag { tag: String, template: usize },
+    /// Text in `tags`, nested outermost first, whose colours have a WCAG contrast ratio below
+    /// the minimum. Found by [`StyleSheet::check_contrast`].
+    LowContrast {
+        tags: String,
+        foreground: (u8, u8, u8),
+        background: (u8, u8, u8),
+        /// The contrast ratio in hundredths, rounded down, e.g. 450 for 4.5:1.
+        ratio: u32,
+    },
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::DuplicateSelector {
+                selector,
+                media: None,
+            } => write!(f, "{} is defined more than once", selector),
+            Diagnostic::DuplicateSelector {
+                selector,
+                media: Some(media),
+            } => write!(
+                f,
+                "{} is defined more than once in @media {}",
+                selector, media
+            ),
+            Diagnostic::ShadowedDeclaration {
+                selector,
+                declaration,
+            } => write!(f, "{} in {} is always overridden", declaration, selector),
+            Diagnostic::NoOpDeclaration {
+                selector,
+                declaration,
+                reason,
+            } => write!(
+                f,
+                "{} in {} has no effect: {}",
+                declaration, selector, reason
+            ),
+            Diagnostic::EmptyRule { selector } => write!(f, "{} has no declarations", selector),
+            Diagnostic::UnusedRule { selector } => {
+                write!(f, "{} is not used by any template", selector)
+            }
+            Diagnostic::UndefinedTag { tag, template } => {
+                write!(f, "<{}> in template {} has no rule", tag, template)
+            }
+            Diagnostic::LowContrast {
+                tags,
+                foreground: (r, g, b),
+                background: (br, bg, bb),
+                ratio,
+            } => write!(
+                f,
+                "{} is #{:02x}{:02x}{:02x} on #{:02x}{:02x}{:02x}, a contrast ratio of {}.{}:1",
+                tags,
+                r,
+                g,
+                b,
+                br,
+                bg,
+                bb,
+                ratio / 100,
+                ratio % 100 / 10
+            ),
+        }
+    }
+}
+
+/// The declarations of a rule, split into property names and the text of each.
+fn declarations(rule: &Rule) -> Vec<(String, String, bool)> {
+    rule.declarations
+        .iter()
+        .map(|declaration| {
+            let text = declaration.to_string();
+            let name = text.split(':').next().unwrap_or_default().to_string();
+            (name, text, declaration.important)
+        })
+        .collect()
+}
+
+impl StyleSheet<'_> {
+    /// Check the stylesheet for duplicate selectors, shadowed, no-op and empty declarations,
+    /// and, if `templates` are given, for rules none of them use and for tags they use that
+    /// have no rule.
+    ///
+    /// ```rust
+    /// use colored_markup::StyleSheet;
+    ///
+    /// let style_sheet = StyleSheet::parse("error { foreground: red } error { foreground: blue }")
+    ///     .unwrap();
+    /// for diagnostic in style_sheet.lint(&["<error>!</error> <path>a</path>"]) {
+    ///     println!("{}", diagnostic);
+    /// }
+    /// ```
+    pub fn lint(&self, templates: &[&str]) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let media = rule.media.as_ref().map(|media| media.to_string());
+                (rule.selector.to_string(), media, declarations(rule))
+            })
+            .collect();
+
+        for (index, (selector, media, declarations)) in rules.iter().enumerate() {
+            let earlier = &rules[..index];
+            if earlier.iter().any(|(s, m, _)| s == selector && m == media) {
+                diagnostics.push(Diagnostic::DuplicateSelector {
+                    selector: selector.clone(),
+                    media: media.clone(),
+                });
+            }
+            if declarations.is_empty() {
+                diagnostics.push(Diagnostic::EmptyRule {
+                    selector: selector.clone(),
+                });
+            }
+            for (position, (name, text, important)) in declarations.iter().enumerate() {
+                // Styles from several declarations are combined rather than replaced.
+                if name == "styles" {
+                    continue;
+                }
+                // Declarations for the same selector that apply wherever this one does, in the
+                // same `@media` query or in none, override it if they come later or if only
+                // they are `!important`.
+                let overridden = rules
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (s, m, _))| s == selector && (m.is_none() || m == media))
+                    .flat_map(|(other, (_, _, declarations))| {
+                        let declarations = declarations.iter().enumerate();
+                        declarations.map(move |(p, declaration)| ((other, p), declaration))
+                    })
+                    .filter(|(_, (other_name, _, _))| other_name == name)
+                    .any(|(order, (_, _, other_important))| {
+                        let later = order > (index, position);
+                        (*other_important && !important) || (later && other_important == important)
+                    });
+                if overridden {
+                    diagnostics.push(Diagnostic::ShadowedDeclaration {
+                        selector: selector.clone(),
+                        declaration: text.clone(),
+                    });
+                } else if let Some(reason) = self.no_op_reason(index, name) {
+                    diagnostics.push(Diagnostic::NoOpDeclaration {
+                        selector: selector.clone(),
+                        declaration: text.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+
+        if templates.is_empty() {
+            return diagnostics;
+        }
+        let mut used = Vec::new();
+        for (template, source) in templates.iter().enumerate() {
+            let Ok(markup) = Markup::parse(source) else {
+                continue;
+            };
+            for part in markup.parts {
+                let Part::OpenTag(tag) = part else {
+                    continue;
+                };
+                if used.iter().any(|(used, _)| *used == tag) {
+                    continue;
+                }
+                used.push((tag, template));
+            }
+        }
+        for rule in &self.rules {
+            let mut names = rule.selector.names.iter().filter(|name| *name != "*");
+            if names.any(|name| !used.iter().any(|(tag, _)| tag == name)) {
+                diagnostics.push(Diagnostic::UnusedRule {
+                    selector: rule.selector.to_string(),
+                });
+            }
+        }
+        for (tag, template) in used {
+            let styled = self.rules.iter().any(|rule| {
+                let subject = rule.selector.names.last();
+                subject.is_some_and(|subject| subject == tag || subject == "*")
+            });
+            if !styled && tag != "reset" {
+                diagnostics.push(Diagnostic::UndefinedTag {
+                    tag: tag.to_string(),
+                    template,
+                });
+            }
+        }
+        diagnostics
+    }
+
+    /// Why the property `name` has no effect in the rule at `index`, if it has none.
+    fn no_op_reason(&self, index: usize, name: &str) -> Option<&'static str> {
+        let rule = &self.rules[index];
+        match name {
+            "content" if rule.selector.pseudo_element.is_none() => {
+                Some("content only applies to ::before and ::after")
+            }
+            "text-align" | "overflow" => {
+                // Any rule for the same tag might set the width.
+                let subject = rule.selector.names.last();
+                let sets_width = self.rules.iter().any(|other| {
+                    other.selector.names.last() == subject
+                        && other.selector.pseudo_element == rule.selector.pseudo_element
+                        && declarations(other)
+                            .iter()
+                            .any(|(name, _, _)| name == "width")
+                });
+                (!sets_width).then_some("it only applies to tags with a width")
+            }
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_lint() {
+    let style_sheet = StyleSheet::parse(
+        "
+        error { foreground: red; styles: bold }
+        @media (colors >= 256) { error { foreground: #ff0000; background: black } }
+        error { foreground: bright-red; styles: underline }
+        path { foreground: cyan !important }
+        path { foreground: blue; content: \"x\" }
+        path::before { content: \"> \" }
+        hint { text-align: right }
+        unused { }
+        ",
+    )
+    .unwrap();
+    let diagnostics =
+        style_sheet.lint(&["<error>!</error> <path>a</path>", "<hint><b>x</b></hint>"]);
+    let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "foreground: red in error is always overridden",
+            "foreground: #ff0000 in error is always overridden",
+            "error is defined more than once",
+            "path is defined more than once",
+            "foreground: blue in path is always overridden",
+            "content: \"x\" in path has no effect: content only applies to ::before and ::after",
+            "text-align: right in hint has no effect: it only applies to tags with a width",
+            "unused has no declarations",
+            "unused is not used by any template",
+            "<b> in template 1 has no rule",
+        ]
+    );
+    assert_eq!(
+        diagnostics[9],
+        Diagnostic::UndefinedTag {
+            tag: "b".to_string(),
+            template: 1
+        }
+    );
+    assert!(StyleSheet::default().lint(&[]).is_empty());
+}
@@ -19,7 +19,7 @@ impl<'a> Markup<'a> {
             static ref REGEX: Regex = Regex::new(
                 r"(?x)
                 (?P<tag><
-                    (?:(?P<open>[a-z]+)|/(?P<close>[a-z]+))
+                    (?:(?P<open>[a-z][a-z0-9_-]*)|/(?P<close>[a-z][a-z0-9_-]*))
                 >)"
             )
             .unwrap();
@@ -90,6 +90,23 @@ fn test_parse_markup() {
     assert_eq!(parts, expectation);
 }
 
+#[test]
+fn test_tag_names() {
+    let parts = Markup::parse("<ext-rs>a</ext-rs><h1>b</h1><3 <-x>")
+        .unwrap()
+        .parts;
+    let expectation = vec![
+        Part::OpenTag("ext-rs"),
+        Part::Text("a"),
+        Part::CloseTag("ext-rs"),
+        Part::OpenTag("h1"),
+        Part::Text("b"),
+        Part::CloseTag("h1"),
+        Part::Text("<3 <-x>"),
+    ];
+    assert_eq!(parts, expectation);
+}
+
 #[test]
 fn test_negative() {
     assert!(!Markup::parse("</oops>").unwrap().is_valid());
@@ -0,0 +1,290 @@
+//! The RGB values that colour functions such as `lighten()` compute with.
+use crate::styles::Color;
+
+/// The RGB values of the sixteen named colours and of the terminal's background and
+/// foreground, plus the sixteen colours of a base16 scheme (`base00` to `base0f`).
+///
+/// Terminals let users choose what the named colours look like, so a palette matching the
+/// user's terminal makes computed colours blend in. The default is xterm's palette, white on
+/// black. Palettes can be imported from the user's terminal configuration, e.g. with
+/// [`Palette::from_base16`] or [`Palette::from_kitty`].
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Palette {
+    colors: [(u8, u8, u8); 16],
+    background: (u8, u8, u8),
+    foreground: (u8, u8, u8),
+    /// The base16 colours, where set.
+    base16: [Option<(u8, u8, u8)>; 16],
+}
+
+/// One of the sixteen base16 colours, from `base00` to `base0f`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct Base16(u8);
+
+impl Base16 {
+    /// Base16 colour `index`, if it is 0 (`base00`) to 15 (`base0f`).
+    pub fn new(index: u8) -> Option<Base16> {
+        (index < 16).then_some(Base16(index))
+    }
+
+    /// The colour's index, 0 to 15.
+    pub fn index(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        let mut colors = [(0, 0, 0); 16];
+        for (index, color) in Color::NAMED.iter().enumerate() {
+            colors[index] = color.rgb();
+        }
+        Palette {
+            colors,
+            background: (0, 0, 0),
+            foreground: Color::White.rgb(),
+            base16: [None; 16],
+        }
+    }
+}
+
+impl Palette {
+    /// The RGB value of `color`.
+    pub fn rgb(&self, color: Color) -> (u8, u8, u8) {
+        match color.ansi_index() {
+            Some(index) => self.colors[index as usize],
+            None => color.rgb(),
+        }
+    }
+
+    /// Set the RGB value of a named colour. True colours are left as they are.
+    pub fn set(&mut self, color: Color, rgb: (u8, u8, u8)) {
+        if let Some(index) = color.ansi_index() {
+            self.colors[index as usize] = rgb;
+        }
+    }
+
+    /// The terminal's background, which `alpha()` blends colours with.
+    pub fn background(&self) -> (u8, u8, u8) {
+        self.background
+    }
+
+    /// Set the terminal's background.
+    pub fn set_background(&mut self, rgb: (u8, u8, u8)) {
+        self.background = rgb;
+    }
+
+    /// The terminal's default text colour.
+    pub fn foreground(&self) -> (u8, u8, u8) {
+        self.foreground
+    }
+
+    /// Set the terminal's default text colour.
+    pub fn set_foreground(&mut self, rgb: (u8, u8, u8)) {
+        self.foreground = rgb;
+    }
+
+    /// The base16 colour `color`. Colours that have not been set are derived from the rest of
+    /// the palette as base16 terminal themes map them: `base00` is the background, `base05`
+    /// the foreground, `base08` red and so on.
+    pub fn base16(&self, color: Base16) -> (u8, u8, u8) {
+        if let Some(rgb) = self.base16[color.index() as usize] {
+            return rgb;
+        }
+        let (background, foreground) = (self.background, self.foreground);
+        match color.index() {
+            0 => background,
+            1 => mix(foreground, background, 0.1),
+            2 => mix(foreground, background, 0.2),
+            3 => self.rgb(Color::BrightBlack),
+            4 => mix(foreground, background, 0.7),
+            5 => foreground,
+            6 => mix(self.rgb(Color::BrightWhite), foreground, 0.5),
+            7 => self.rgb(Color::BrightWhite),
+            8 => self.rgb(Color::Red),
+            9 => mix(self.rgb(Color::Red), self.rgb(Color::Yellow), 0.5),
+            10 => self.rgb(Color::Yellow),
+            11 => self.rgb(Color::Green),
+            12 => self.rgb(Color::Cyan),
+            13 => self.rgb(Color::Blue),
+            14 => self.rgb(Color::Magenta),
+            _ => mix(self.rgb(Color::Red), background, 0.6),
+        }
+    }
+
+    /// Set the base16 colour `color`.
+    pub fn set_base16(&mut self, color: Base16, rgb: (u8, u8, u8)) {
+        self.base16[color.index() as usize] = Some(rgb);
+    }
+
+    /// The colour `palette(name)` refers to in a stylesheet: a named colour such as
+    /// `bright-red`, `background`, `foreground`, or a base16 colour such as `base08`.
+    pub fn get(&self, name: &str) -> Option<(u8, u8, u8)> {
+        let name = name.to_ascii_lowercase();
+        match name.as_str() {
+            "background" => return Some(self.background),
+            "foreground" => return Some(self.foreground),
+            _ => {}
+        }
+        if let Some(index) = name.strip_prefix("base0") {
+            let index = u8::from_str_radix(index, 16)
+                .ok()
+                .filter(|_| index.len() == 1)?;
+            return Some(self.base16(Base16::new(index)?));
+        }
+        let color = Color::NAMED
+            .into_iter()
+            .find(|color| color.to_string() == name)?;
+        Some(self.rgb(color))
+    }
+
+    /// The named colour closest to `rgb`.
+    pub(crate) fn nearest(&self, (r, g, b): (u8, u8, u8)) -> Color {
+        let distance = |color: &&Color| {
+            let (r2, g2, b2) = self.rgb(**color);
+            let square = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+            square(r, r2) + square(g, g2) + square(b, b2)
+        };
+        *Color::NAMED.iter().min_by_key(distance).unwrap()
+    }
+}
+
+/// The closest of the 256 colours to `rgb` that are not named colours: the 6×6×6 colour cube
+/// (16 to 231) or the grey ramp (232 to 255).
+pub(crate) fn ansi256((r, g, b): (u8, u8, u8)) -> u8 {
+    const LEVELS: [i32; 6] = [0, 95, 135, 175, 215, 255];
+    let square = |a: i32, b: u8| (a - b as i32).pow(2);
+    let level = |channel: u8| {
+        (0..6)
+            .min_by_key(|&index| square(LEVELS[index], channel))
+            .unwrap()
+    };
+    let (cr, cg, cb) = (level(r), level(g), level(b));
+    let cube_distance = square(LEVELS[cr], r) + square(LEVELS[cg], g) + square(LEVELS[cb], b);
+    let average = (r as i32 + g as i32 + b as i32) / 3;
+    let grey = ((average - 3).max(0) / 10).min(23);
+    let level = 8 + grey * 10;
+    let grey_distance = square(level, r) + square(level, g) + square(level, b);
+    if grey_distance < cube_distance {
+        232 + grey as u8
+    } else {
+        16 + (36 * cr + 6 * cg + cb) as u8
+    }
+}
+
+#[test]
+fn test_ansi256() {
+    assert_eq!(ansi256((255, 0, 0)), 196);
+    assert_eq!(ansi256((0, 0, 0)), 16);
+    assert_eq!(ansi256((95, 135, 175)), 67);
+    assert_eq!(ansi256((128, 128, 128)), 244);
+    assert_eq!(ansi256((255, 255, 255)), 231);
+}
+
+/// Hue (0 to 360), saturation and lightness (0 to 1).
+pub(crate) fn to_hsl((r, g, b): (u8, u8, u8)) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    if max == min {
+        return (0.0, 0.0, lightness);
+    }
+    let delta = max - min;
+    let saturation = if lightness > 0.5 {
+        delta / (2.0 - max - min)
+    } else {
+        delta / (max + min)
+    };
+    let hue = if max == r {
+        (g - b) / delta + if g < b { 6.0 } else { 0.0 }
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+    (hue * 60.0, saturation, lightness)
+}
+
+pub(crate) fn from_hsl((hue, saturation, lightness): (f32, f32, f32)) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f32| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// `weight` of `a` mixed with `1 - weight` of `b`.
+pub(crate) fn mix(a: (u8, u8, u8), b: (u8, u8, u8), weight: f32) -> (u8, u8, u8) {
+    let weight = weight.clamp(0.0, 1.0);
+    let channel = |a: u8, b: u8| (a as f32 * weight + b as f32 * (1.0 - weight)).round() as u8;
+    (channel(a.0, b.0), channel(a.1, b.1), channel(a.2, b.2))
+}
+
+/// The WCAG relative luminance, from 0 for black to 1 for white.
+fn luminance((r, g, b): (u8, u8, u8)) -> f32 {
+    let channel = |value: u8| {
+        let value = value as f32 / 255.0;
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(r) + 0.7152 * channel(g) + 0.0722 * channel(b)
+}
+
+/// The WCAG contrast ratio between two colours, from 1 (none) to 21 (black and white). WCAG
+/// asks for at least 4.5 for normal text and 3 for large text.
+pub fn contrast_ratio(a: (u8, u8, u8), b: (u8, u8, u8)) -> f32 {
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+#[test]
+fn test_palette() {
+    let mut palette = Palette::default();
+    assert_eq!(palette.rgb(Color::Red), (205, 0, 0));
+    palette.set(Color::Red, (220, 50, 47));
+    assert_eq!(palette.rgb(Color::Red), (220, 50, 47));
+    assert_eq!(
+        palette.rgb(Color::TrueColor { r: 1, g: 2, b: 3 }),
+        (1, 2, 3)
+    );
+    assert_eq!(Palette::default().nearest((250, 40, 40)), Color::BrightRed);
+    assert_eq!(Palette::default().nearest((190, 20, 10)), Color::Red);
+    assert_eq!(palette.nearest((120, 120, 130)), Color::BrightBlack);
+
+    for rgb in [(255, 0, 0), (18, 52, 86), (200, 200, 200), (0, 0, 0)] {
+        assert_eq!(from_hsl(to_hsl(rgb)), rgb);
+    }
+    assert_eq!(to_hsl((0, 0, 255)), (240.0, 1.0, 0.5));
+    assert_eq!(mix((255, 0, 0), (0, 0, 255), 0.3), (77, 0, 179));
+    assert_eq!(contrast_ratio((0, 0, 0), (255, 255, 255)).round(), 21.0);
+    // #767676 is the lightest grey with a ratio of at least 4.5 on white.
+    assert!(contrast_ratio((118, 118, 118), (255, 255, 255)) >= 4.5);
+    assert!(contrast_ratio((119, 119, 119), (255, 255, 255)) < 4.5);
+    assert_eq!(contrast_ratio((10, 20, 30), (10, 20, 30)), 1.0);
+
+    assert_eq!(palette.get("red"), Some((220, 50, 47)));
+    assert_eq!(palette.get("Bright-Blue"), Some((92, 92, 255)));
+    assert_eq!(palette.get("foreground"), Some((229, 229, 229)));
+    assert_eq!(palette.get("base08"), Some((220, 50, 47)));
+    assert_eq!(palette.get("base00"), Some((0, 0, 0)));
+    palette.set_base16(Base16::new(0).unwrap(), (46, 52, 64));
+    assert_eq!(palette.get("base00"), Some((46, 52, 64)));
+    assert_eq!(Base16::new(15).map(Base16::index), Some(15));
+    assert_eq!(Base16::new(16), None);
+    assert_eq!(palette.get("base0g"), None);
+    assert_eq!(palette.get("base010"), None);
+    assert_eq!(palette.get("#ff0000"), None);
+}
@@ -0,0 +1,325 @@
+//! Palettes imported from terminal colour schemes.
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+
+use crate::palette::{Base16, Palette};
+use crate::styles::Color;
+
+/// The base16 colour each named colour is set to, as base16 terminal themes do.
+const BASE16_ANSI: [u8; 16] = [0, 8, 11, 10, 13, 14, 12, 5, 3, 8, 11, 10, 13, 14, 12, 7];
+
+/// The names of the eight normal colours, in ANSI order.
+const ANSI_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+/// An RGB value written as `#rrggbb`, `0xrrggbb`, `rrggbb` or `rgb:rr/gg/bb`, optionally quoted.
+fn rgb(value: &str) -> Option<(u8, u8, u8)> {
+    let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+    if let Some(channels) = value.strip_prefix("rgb:") {
+        let channels: Vec<u8> = channels
+            .split('/')
+            .map(|channel| {
+                // X accepts one to four hex digits per channel.
+                if !(1..=4).contains(&channel.len()) {
+                    return None;
+                }
+                let max = 16u32.pow(channel.len() as u32);
+                let value = u32::from_str_radix(channel, 16).ok()?;
+                Some((value * 255 / (max - 1)) as u8)
+            })
+            .collect::<Option<_>>()?;
+        return match channels[..] {
+            [r, g, b] => Some((r, g, b)),
+            _ => None,
+        };
+    }
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).unwrap();
+    Some((channel(0), channel(2), channel(4)))
+}
+
+/// A YAML or TOML value, without its quotes or any comment after it.
+fn scalar(value: &str) -> &str {
+    let value = value.trim();
+    match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+        _ => value.split(" #").next().unwrap_or_default().trim(),
+    }
+}
+
+/// Set the colour named `key` (`color0` to `color15`, `background` or `foreground`), returning
+/// whether the key was one of those.
+fn set_terminal_color(palette: &mut Palette, key: &str, value: &str) -> Result<bool> {
+    let index = match key {
+        "background" | "foreground" => None,
+        _ => match key.strip_prefix("color").map(str::parse::<usize>) {
+            Some(Ok(index)) if index < 16 => Some(index),
+            _ => return Ok(false),
+        },
+    };
+    let rgb = rgb(value).ok_or_else(|| anyhow!("Invalid colour {:?} for {}", value, key))?;
+    match index {
+        Some(index) => palette.set(Color::NAMED[index], rgb),
+        None if key == "background" => palette.set_background(rgb),
+        None => palette.set_foreground(rgb),
+    }
+    Ok(true)
+}
+
+fn found(palette: Palette, count: usize, format: &str) -> Result<Palette> {
+    if count == 0 {
+        return Err(anyhow!("No colours found in {}", format));
+    }
+    Ok(palette)
+}
+
+impl Palette {
+    /// A palette from a base16 scheme in YAML, either the classic format with `base00` to
+    /// `base0F` at the top level or the newer one with them under `palette:`. The named colours,
+    /// background and foreground are set from the scheme as base16 terminal themes set them.
+    pub fn from_base16(yaml: &str) -> Result<Palette> {
+        let mut palette = Palette::default();
+        let mut base16 = [None; 16];
+        for line in yaml.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let Some(index) = key.strip_prefix("base0") else {
+                continue;
+            };
+            let Some(index) = u8::from_str_radix(index, 16)
+                .ok()
+                .filter(|_| index.len() == 1)
+            else {
+                continue;
+            };
+            let value = scalar(value);
+            let rgb =
+                rgb(value).ok_or_else(|| anyhow!("Invalid colour {:?} for {}", value, key))?;
+            base16[index as usize] = Some(rgb);
+        }
+        let count = base16.iter().flatten().count();
+        for (index, rgb) in (0..).zip(base16) {
+            if let (Some(color), Some(rgb)) = (Base16::new(index), rgb) {
+                palette.set_base16(color, rgb);
+            }
+        }
+        let derived = palette.clone();
+        let base16 = |index| derived.base16(Base16::new(index).unwrap());
+        for (color, base) in Color::NAMED.iter().zip(BASE16_ANSI) {
+            palette.set(*color, base16(base));
+        }
+        palette.set_background(base16(0));
+        palette.set_foreground(base16(5));
+        found(palette, count, "base16 scheme")
+    }
+
+    /// A palette from the `[colors.primary]`, `[colors.normal]` and `[colors.bright]` tables of
+    /// an Alacritty configuration in TOML.
+    pub fn from_alacritty(toml: &str) -> Result<Palette> {
+        let mut palette = Palette::default();
+        let mut count = 0;
+        let mut table = String::new();
+        for line in toml.lines() {
+            let line = line.trim();
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                table = name.replace(['"', '\'', ' '], "");
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let key = format!("{}.{}", table, key.replace(['"', '\'', ' '], ""));
+            let value = scalar(value);
+            let key = match key.strip_prefix("colors.") {
+                Some(key) => key,
+                None => continue,
+            };
+            let color = match key.split_once('.') {
+                Some(("primary", name @ ("background" | "foreground"))) => name.to_string(),
+                Some((layer @ ("normal" | "bright"), name)) => {
+                    let Some(index) = ANSI_NAMES.iter().position(|n| *n == name) else {
+                        continue;
+                    };
+                    let index = if layer == "bright" { index + 8 } else { index };
+                    format!("color{}", index)
+                }
+                _ => continue,
+            };
+            if set_terminal_color(&mut palette, &color, value)? {
+                count += 1;
+            }
+        }
+        found(palette, count, "Alacritty configuration")
+    }
+
+    /// A palette from the `color0` to `color15`, `background` and `foreground` settings of a
+    /// kitty configuration.
+    pub fn from_kitty(conf: &str) -> Result<Palette> {
+        let mut palette = Palette::default();
+        let mut count = 0;
+        for line in conf.lines() {
+            let mut words = line.split_whitespace();
+            let (Some(key), Some(value)) = (words.next(), words.next()) else {
+                continue;
+            };
+            if set_terminal_color(&mut palette, key, value)? {
+                count += 1;
+            }
+        }
+        found(palette, count, "kitty configuration")
+    }
+
+    /// A palette from the `color0` to `color15`, `background` and `foreground` resources in
+    /// X resources, e.g. `*.color1: #cc6666` or `URxvt*background: base00` after
+    /// `#define base00 #1d1f21`.
+    pub fn from_xresources(resources: &str) -> Result<Palette> {
+        let mut palette = Palette::default();
+        let mut count = 0;
+        let mut defines = HashMap::new();
+        for line in resources.lines() {
+            let line = line.trim();
+            if let Some(define) = line.strip_prefix("#define") {
+                let mut words = define.split_whitespace();
+                if let (Some(name), Some(value)) = (words.next(), words.next()) {
+                    defines.insert(name, value);
+                }
+                continue;
+            }
+            if line.starts_with('!') || line.starts_with('#') {
+                continue;
+            }
+            let Some((resource, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = resource
+                .rsplit(['*', '.'])
+                .next()
+                .unwrap_or_default()
+                .trim();
+            let value = value.trim();
+            let value = defines.get(value).copied().unwrap_or(value);
+            if set_terminal_color(&mut palette, key, value)? {
+                count += 1;
+            }
+        }
+        found(palette, count, "X resources")
+    }
+}
+
+#[test]
+fn test_rgb() {
+    assert_eq!(rgb("#ff8800"), Some((255, 136, 0)));
+    assert_eq!(rgb("'0xFF8800'"), Some((255, 136, 0)));
+    assert_eq!(rgb("\"ff8800\""), Some((255, 136, 0)));
+    assert_eq!(rgb("rgb:ff/88/00"), Some((255, 136, 0)));
+    assert_eq!(rgb("rgb:ffff/8888/0"), Some((255, 136, 0)));
+    assert_eq!(rgb("rgb:fffffff/0/0"), None);
+    assert_eq!(rgb("rgb://0"), None);
+    assert_eq!(rgb("#ff88"), None);
+    assert_eq!(rgb("red"), None);
+}
+
+#[test]
+fn test_from_base16() {
+    let yaml = r#"
+scheme: "Nord"
+author: "arcticicestudio"
+base00: "2E3440"
+base05: "E5E9F0" # foreground
+base08: "88C0D0"
+base0B: "bf616a"
+"#;
+    let palette = Palette::from_base16(yaml).unwrap();
+    assert_eq!(palette.background(), (46, 52, 64));
+    assert_eq!(palette.foreground(), (229, 233, 240));
+    assert_eq!(palette.rgb(Color::Red), (136, 192, 208));
+    assert_eq!(palette.rgb(Color::BrightRed), (136, 192, 208));
+    assert_eq!(palette.rgb(Color::Green), (191, 97, 106));
+    assert_eq!(palette.get("base0b"), Some((191, 97, 106)));
+    // Colours missing from the scheme are derived from those it has.
+    assert_eq!(palette.rgb(Color::Black), (46, 52, 64));
+
+    let tinted = "system: \"base16\"\npalette:\n  base00: \"#2e3440\"\n";
+    let palette = Palette::from_base16(tinted).unwrap();
+    assert_eq!(palette.get("base00"), Some((46, 52, 64)));
+    assert!(Palette::from_base16("scheme: nothing").is_err());
+    assert!(Palette::from_base16("base00: nope").is_err());
+}
+
+#[test]
+fn test_from_alacritty() {
+    let toml = r##"
+[window]
+opacity = 0.9
+
+[colors.primary]
+background = "#1d1f21"
+foreground = '0xc5c8c6' # comment
+
+[colors.normal]
+red = "#cc6666"
+
+[colors.bright]
+red = "#d54e53"
+"##;
+    let palette = Palette::from_alacritty(toml).unwrap();
+    assert_eq!(palette.background(), (29, 31, 33));
+    assert_eq!(palette.foreground(), (197, 200, 198));
+    assert_eq!(palette.rgb(Color::Red), (204, 102, 102));
+    assert_eq!(palette.rgb(Color::BrightRed), (213, 78, 83));
+    assert_eq!(palette.rgb(Color::Blue), Color::Blue.rgb());
+
+    let dotted = "[colors]\nnormal.blue = \"#81a2be\"\n";
+    let palette = Palette::from_alacritty(dotted).unwrap();
+    assert_eq!(palette.rgb(Color::Blue), (129, 162, 190));
+    assert!(Palette::from_alacritty("[colors.normal]\nred = \"nope\"").is_err());
+    assert!(Palette::from_alacritty("[window]\nopacity = 0.9").is_err());
+}
+
+#[test]
+fn test_from_kitty() {
+    colored::control::set_override(true);
+    let conf =
+        "# Tomorrow Night\nbackground #1d1f21\ncolor1  #cc6666\ncolor9 #d54e53\nfont_size 12\n";
+    let palette = Palette::from_kitty(conf).unwrap();
+    assert_eq!(palette.background(), (29, 31, 33));
+    assert_eq!(palette.rgb(Color::Red), (204, 102, 102));
+    assert_eq!(palette.rgb(Color::BrightRed), (213, 78, 83));
+    assert!(Palette::from_kitty("color1 red").is_err());
+
+    let mut style_sheet =
+        crate::StyleSheet::parse("error { foreground: palette(red); background: base00 }").unwrap();
+    style_sheet.set_palette(palette);
+    assert_eq!(
+        style_sheet.render("<error>!</error>").unwrap(),
+        "\u{1b}[48;2;29;31;33;38;2;204;102;102m!\u{1b}[0m"
+    );
+}
+
+#[test]
+fn test_from_xresources() {
+    let resources = "
+! Tomorrow Night
+#define base00 #1d1f21
+*.background: base00
+URxvt*color1: #cc6666
+*color9:      rgb:d5/4e/53
+URxvt.font: xft:Monospace
+";
+    let palette = Palette::from_xresources(resources).unwrap();
+    assert_eq!(palette.background(), (29, 31, 33));
+    assert_eq!(palette.rgb(Color::Red), (204, 102, 102));
+    assert_eq!(palette.rgb(Color::BrightRed), (213, 78, 83));
+    assert!(Palette::from_xresources("*.color1: base08").is_err());
+    assert!(Palette::from_xresources("*.color1: rgb:fffffff/0/0").is_err());
+}
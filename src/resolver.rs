@@ -0,0 +1,121 @@
+//! Resolvers locate the stylesheets named by `@import` rules.
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The source of an imported stylesheet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedImport {
+    /// A unique identifier for the stylesheet, such as its path. Imports nested inside this
+    /// stylesheet are resolved relative to it, and it is used to detect import cycles.
+    pub id: String,
+    /// The stylesheet source.
+    pub source: String,
+}
+
+/// A trait for loading the stylesheets named by `@import "path";` rules.
+pub trait StyleSheetResolver {
+    /// Load the stylesheet `path`, imported by the stylesheet with id `importer` (or by the root
+    /// stylesheet if `None`).
+    fn resolve(&self, path: &str, importer: Option<&str>) -> Result<ResolvedImport>;
+}
+
+/// A resolver that loads stylesheets from the filesystem, relative to the importing file.
+#[derive(Debug, Clone)]
+pub struct FileSystemResolver {
+    root: PathBuf,
+}
+
+impl FileSystemResolver {
+    /// Create a resolver where imports from the root stylesheet are relative to `root`.
+    pub fn new(root: impl Into<PathBuf>) -> FileSystemResolver {
+        FileSystemResolver { root: root.into() }
+    }
+}
+
+impl FileSystemResolver {
+    /// The file `path` refers to when imported by `importer`.
+    pub(crate) fn path(&self, path: &str, importer: Option<&str>) -> PathBuf {
+        let base = importer
+            .and_then(|importer| Path::new(importer).parent())
+            .unwrap_or(&self.root);
+        base.join(path)
+    }
+}
+
+impl StyleSheetResolver for FileSystemResolver {
+    fn resolve(&self, path: &str, importer: Option<&str>) -> Result<ResolvedImport> {
+        let path = self.path(path, importer);
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to import {}", path.display()))?;
+        Ok(ResolvedImport {
+            id: path.to_string_lossy().into_owned(),
+            source,
+        })
+    }
+}
+
+/// A resolver that looks stylesheets up by name in memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    sheets: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    /// Create an empty resolver.
+    pub fn new() -> MemoryResolver {
+        MemoryResolver::default()
+    }
+
+    /// Add (or replace) the stylesheet `source` importable as `path`.
+    pub fn insert(&mut self, path: impl Into<String>, source: impl Into<String>) {
+        self.sheets.insert(path.into(), source.into());
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for MemoryResolver {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut resolver = MemoryResolver::new();
+        for (path, source) in iter {
+            resolver.insert(path, source);
+        }
+        resolver
+    }
+}
+
+impl StyleSheetResolver for MemoryResolver {
+    fn resolve(&self, path: &str, _importer: Option<&str>) -> Result<ResolvedImport> {
+        let source = self
+            .sheets
+            .get(path)
+            .ok_or_else(|| anyhow!("Failed to import {}", path))?;
+        Ok(ResolvedImport {
+            id: path.to_string(),
+            source: source.clone(),
+        })
+    }
+}
+
+#[test]
+fn test_memory_resolver() {
+    let resolver = MemoryResolver::from_iter([("base.css", "r { foreground: red }")]);
+    assert_eq!(
+        resolver.resolve("base.css", None).unwrap().source,
+        "r { foreground: red }"
+    );
+    assert!(resolver.resolve("missing.css", None).is_err());
+}
+
+#[test]
+fn test_file_system_resolver() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    std::fs::create_dir_all(dir.join("themes")).unwrap();
+    std::fs::write(dir.join("themes/base.css"), "@import \"colors.css\";").unwrap();
+    std::fs::write(dir.join("themes/colors.css"), "r { foreground: red }").unwrap();
+
+    let resolver = FileSystemResolver::new(dir);
+    let base = resolver.resolve("themes/base.css", None).unwrap();
+    let colors = resolver.resolve("colors.css", Some(&base.id)).unwrap();
+    assert_eq!(colors.source, "r { foreground: red }");
+}
@@ -0,0 +1,101 @@
+use std::borrow::Cow;
+use std::fmt;
+
+/// Generated content inserted before or after a tag's content.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PseudoElement {
+    Before,
+    After,
+}
+
+/// A selector such as `error`, `error path`, `*` or `error::before`: tag names separated by the
+/// descendant combinator. The last name is the tag the rule applies to.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct Selector<'a> {
+    pub(crate) names: Vec<Cow<'a, str>>,
+    pub(crate) pseudo_element: Option<PseudoElement>,
+}
+
+impl<'a> Selector<'a> {
+    /// A selector for a single tag.
+    pub(crate) fn tag(name: impl Into<Cow<'a, str>>) -> Selector<'a> {
+        Selector::new(vec![name.into()], None)
+    }
+
+    pub(crate) fn new(
+        names: Vec<Cow<'a, str>>,
+        pseudo_element: Option<PseudoElement>,
+    ) -> Selector<'a> {
+        Selector {
+            names,
+            pseudo_element,
+        }
+    }
+
+    pub(crate) fn into_owned(self) -> Selector<'static> {
+        let names = self
+            .names
+            .into_iter()
+            .map(|name| Cow::Owned(name.into_owned()));
+        Selector::new(names.collect(), self.pseudo_element)
+    }
+
+    /// The number of tag names in the selector, ignoring `*`. Rules with more specific selectors
+    /// take precedence regardless of their order.
+    pub(crate) fn specificity(&self) -> usize {
+        self.names.iter().filter(|name| *name != "*").count()
+    }
+
+    /// Whether the selector matches the innermost tag of `tags`, which runs outermost first.
+    pub(crate) fn matches(&self, tags: &[&str]) -> bool {
+        let Some((tag, mut ancestors)) = tags.split_last() else {
+            return false;
+        };
+        let Some((subject, names)) = self.names.split_last() else {
+            return false;
+        };
+        if !name_matches(subject, tag) {
+            return false;
+        }
+        // Match the remaining names right to left, each against the nearest ancestor that fits.
+        for name in names.iter().rev() {
+            match ancestors.iter().rposition(|tag| name_matches(name, tag)) {
+                Some(index) => ancestors = &ancestors[..index],
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+impl fmt::Display for Selector<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.names.join(" "))?;
+        match self.pseudo_element {
+            Some(PseudoElement::Before) => write!(f, "::before"),
+            Some(PseudoElement::After) => write!(f, "::after"),
+            None => Ok(()),
+        }
+    }
+}
+
+fn name_matches(name: &str, tag: &str) -> bool {
+    name == "*" || name == tag
+}
+
+#[test]
+fn test_selector_matches() {
+    let selector =
+        |names: &[&'static str]| Selector::new(names.iter().map(|n| (*n).into()).collect(), None);
+    assert!(selector(&["path"]).matches(&["path"]));
+    assert!(selector(&["path"]).matches(&["error", "path"]));
+    assert!(!selector(&["path"]).matches(&["path", "error"]));
+    assert!(selector(&["error", "path"]).matches(&["error", "b", "path"]));
+    assert!(!selector(&["error", "path"]).matches(&["path"]));
+    assert!(!selector(&["error", "b", "path"]).matches(&["b", "error", "path"]));
+    assert!(selector(&["*"]).matches(&["anything"]));
+    assert!(!selector(&["*"]).matches(&[]));
+
+    assert_eq!(selector(&["*"]).specificity(), 0);
+    assert_eq!(selector(&["error", "*", "path"]).specificity(), 2);
+}
@@ -0,0 +1,364 @@
+//! `serde` support, behind the `serde` feature.
+//!
+//! Colours are strings such as `"bright-red"` or `"#ff8800"`, and styles strings such as
+//! `"bold"`. A stylesheet is either a string of stylesheet source, or a map from selectors to
+//! their declarations, so it can be written directly in a configuration file:
+//!
+//! ```toml
+//! [theme.":root"]
+//! --accent = "#ff8800"
+//!
+//! [theme.error]
+//! foreground = "red"
+//! styles = ["bold", "underline"]
+//!
+//! [theme."@media (colors >= 256)".error]
+//! foreground = "var(--accent)"
+//! ```
+//!
+//! Declaration values are written as in a stylesheet, including any `!important`, and lists
+//! are joined with spaces. A selector can also map to a list of declaration maps, such as a
+//! TOML array of tables, for several rules with the same selector. Serializing a stylesheet
+//! writes the map form, with a list wherever a selector has several rules or a rule declares
+//! a property more than once. Reading it back gives the same styles, though rules may be
+//! split differently.
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+use crate::declarations::{Declaration, Property};
+use crate::styles::*;
+use crate::StyleSheet;
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Styles {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Styles {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Styles, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// The map form of a stylesheet, in order.
+enum Tree {
+    Value(String),
+    List(Vec<Tree>),
+    Map(Vec<(String, Tree)>),
+}
+
+impl Serialize for Tree {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Tree::Value(value) => serializer.serialize_str(value),
+            Tree::List(values) => values.serialize(serializer),
+            Tree::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+struct TreeVisitor;
+
+impl<'de> Visitor<'de> for TreeVisitor {
+    type Value = Tree;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a stylesheet, or a map of selectors to declarations")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Tree, E> {
+        Ok(Tree::Value(value.to_string()))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Tree, E> {
+        Ok(Tree::Value(value.to_string()))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Tree, E> {
+        Ok(Tree::Value(value.to_string()))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Tree, E> {
+        Ok(Tree::Value(value.to_string()))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Tree, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element::<Tree>()? {
+            values.push(value);
+        }
+        Ok(Tree::List(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Tree, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry::<String, Tree>()? {
+            entries.push(entry);
+        }
+        Ok(Tree::Map(entries))
+    }
+}
+
+impl<'de> Deserialize<'de> for Tree {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Tree, D::Error> {
+        deserializer.deserialize_any(TreeVisitor)
+    }
+}
+
+/// Write the map form of a stylesheet as stylesheet source. Some formats do not keep the order
+/// of keys, so `@media` blocks are written after the other rules, which they usually override.
+fn to_source(entries: &[(String, Tree)]) -> Result<String, String> {
+    let mut source = String::new();
+    let mut media = String::new();
+    for (key, tree) in entries {
+        if key.starts_with("@media") {
+            let Tree::Map(entries) = tree else {
+                return Err(format!("Expected a map of rules for {}", key));
+            };
+            media.push_str(&format!("{} {{ {} }}\n", key, to_source(entries)?));
+            continue;
+        }
+        let blocks = match tree {
+            Tree::Map(_) => std::slice::from_ref(tree),
+            Tree::List(blocks) => blocks,
+            Tree::Value(_) => return Err(format!("Expected a map of declarations for {}", key)),
+        };
+        for block in blocks {
+            let Tree::Map(declarations) = block else {
+                return Err(format!("Expected a map of declarations for {}", key));
+            };
+            source.push_str(&format!("{} {{ ", key));
+            for (name, value) in declarations {
+                let value = match value {
+                    Tree::Value(value) => value.clone(),
+                    Tree::List(values) => {
+                        let values = values.iter().map(|value| match value {
+                            Tree::Value(value) => Ok(value.as_str()),
+                            _ => Err(format!("Expected a value for {} in {}", name, key)),
+                        });
+                        values.collect::<Result<Vec<_>, _>>()?.join(" ")
+                    }
+                    Tree::Map(_) => {
+                        return Err(format!("Expected a value for {} in {}", name, key))
+                    }
+                };
+                let name = match name.strip_prefix("--") {
+                    None if key == ":root" => format!("--{}", name),
+                    _ => name.clone(),
+                };
+                source.push_str(&format!("{}: {}; ", name, value));
+            }
+            source.push_str("}\n");
+        }
+    }
+    source.push_str(&media);
+    Ok(source)
+}
+
+impl<'de> Deserialize<'de> for StyleSheet<'static> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = match Tree::deserialize(deserializer)? {
+            Tree::Value(source) => source,
+            Tree::Map(entries) => to_source(&entries).map_err(de::Error::custom)?,
+            Tree::List(_) => return Err(de::Error::custom("Expected a stylesheet")),
+        };
+        StyleSheet::parse_owned(source).map_err(de::Error::custom)
+    }
+}
+
+/// A declaration's value in the map form: normal `styles` as a list, and anything else as
+/// it is written in a stylesheet.
+fn declaration_value(declaration: &Declaration) -> Tree {
+    let property = &declaration.property;
+    match property {
+        Property::Styles(styles) if !declaration.important => {
+            let styles = styles.iter().map(|style| Tree::Value(style.to_string()));
+            Tree::List(styles.collect())
+        }
+        Property::RemoveStyles(styles) if !declaration.important => {
+            let styles = styles
+                .iter()
+                .map(|style| Tree::Value(format!("-{}", style)));
+            Tree::List(styles.collect())
+        }
+        _ if declaration.important => Tree::Value(format!("{} !important", property.value())),
+        _ => Tree::Value(property.value()),
+    }
+}
+
+/// The rules for `selector` in `entries`, each a map of declarations, added if needed.
+fn rules<'e>(entries: &'e mut Vec<(String, Tree)>, selector: &str) -> &'e mut Vec<Tree> {
+    let index = match entries.iter().position(|(key, _)| key == selector) {
+        Some(index) => index,
+        None => {
+            entries.push((selector.to_string(), Tree::List(Vec::new())));
+            entries.len() - 1
+        }
+    };
+    match &mut entries[index].1 {
+        Tree::List(rules) => rules,
+        _ => unreachable!(),
+    }
+}
+
+/// Write a selector's only rule as a map rather than a list of one, recursing into `@media`.
+fn simplify(entries: &mut [(String, Tree)]) {
+    for (_, tree) in entries {
+        match tree {
+            Tree::List(rules) if rules.len() == 1 => *tree = rules.pop().unwrap(),
+            Tree::Map(entries) => simplify(entries),
+            _ => {}
+        }
+    }
+}
+
+impl Serialize for StyleSheet<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entries = Vec::new();
+        if !self.variables.is_empty() {
+            let mut variables: Vec<_> = self.variables.iter().collect();
+            variables.sort_by_key(|(name, _)| *name);
+            let variables = variables
+                .into_iter()
+                .map(|(name, value)| (format!("--{}", name), Tree::Value(value.to_string())));
+            entries.push((":root".to_string(), Tree::Map(variables.collect())));
+        }
+        for rule in &self.rules {
+            let scope = match &rule.media {
+                Some(media) => {
+                    let key = format!("@media {}", media);
+                    if !entries.iter().any(|(existing, _)| *existing == key) {
+                        entries.push((key.clone(), Tree::Map(Vec::new())));
+                    }
+                    match entries.iter_mut().find(|(existing, _)| *existing == key) {
+                        Some((_, Tree::Map(entries))) => entries,
+                        _ => unreachable!(),
+                    }
+                }
+                None => &mut entries,
+            };
+            let rules = rules(scope, &rule.selector.to_string());
+            // A property declared twice in a rule starts another map for the same selector.
+            let mut block: Vec<(String, Tree)> = Vec::new();
+            for declaration in &rule.declarations {
+                let name = declaration.property.name();
+                if block.iter().any(|(existing, _)| existing == name) {
+                    rules.push(Tree::Map(std::mem::take(&mut block)));
+                }
+                block.push((name.to_string(), declaration_value(declaration)));
+            }
+            rules.push(Tree::Map(block));
+        }
+        simplify(&mut entries);
+        Tree::Map(entries).serialize(serializer)
+    }
+}
+
+#[test]
+fn test_serde() {
+    let red: Color = serde_json::from_str("\"red\"").unwrap();
+    assert_eq!(red, Color::Red);
+    assert_eq!(
+        serde_json::to_string(&Color::TrueColor {
+            r: 255,
+            g: 136,
+            b: 0
+        })
+        .unwrap(),
+        "\"#ff8800\""
+    );
+    assert!(serde_json::from_str::<Color>("\"nope\"").is_err());
+    let styles: Vec<Styles> = serde_json::from_str(r#"["bold", "rapid-blink"]"#).unwrap();
+    assert_eq!(styles, vec![Styles::Bold, Styles::RapidBlink]);
+    assert_eq!(
+        serde_json::to_string(&styles).unwrap(),
+        r#"["bold","rapid-blink"]"#
+    );
+
+    #[derive(serde::Deserialize)]
+    struct Config {
+        theme: StyleSheet<'static>,
+    }
+    let config: Config = toml::from_str(
+        r##"
+        [theme.":root"]
+        accent = "#ff8800"
+
+        [theme.error]
+        foreground = "var(--accent)"
+        styles = ["bold", "underline"]
+        width = 8
+
+        [theme."@media (colors >= 256)".error]
+        foreground = "red !important"
+        "##,
+    )
+    .unwrap();
+    let expected = StyleSheet::parse(
+        ":root { --accent: #ff8800 }
+        error { foreground: var(--accent); styles: bold underline; width: 8 }
+        @media (colors >= 256) { error { foreground: red !important } }",
+    )
+    .unwrap();
+    assert_eq!(config.theme, expected);
+
+    let json = serde_json::to_string(&expected).unwrap();
+    assert_eq!(
+        json,
+        r##"{":root":{"--accent":"#ff8800"},"error":{"foreground":"var(--accent)","styles":["bold","underline"],"width":"8"},"@media (colors >= 256)":{"error":{"foreground":"red !important"}}}"##
+    );
+    assert_eq!(serde_json::from_str::<StyleSheet>(&json).unwrap(), expected);
+    let source: StyleSheet = serde_json::from_str(r#""error { foreground: red }""#).unwrap();
+    assert_eq!(
+        source,
+        StyleSheet::parse("error { foreground: red }").unwrap()
+    );
+    assert!(serde_json::from_str::<StyleSheet>(r#"{"error": "red"}"#).is_err());
+
+    // Several rules for a selector, and `!important`, survive a round trip.
+    let style_sheet = StyleSheet::parse(
+        "error { styles: bold !important } error { styles: italic -dimmed }
+        path { foreground: red; font-weight: bold; styles: underline }",
+    )
+    .unwrap();
+    let json = serde_json::to_string(&style_sheet).unwrap();
+    assert_eq!(
+        json,
+        r#"{"error":[{"styles":"bold !important"},{"styles":["italic"]},{"styles":["-dimmed"]}],"path":[{"foreground":"red","styles":["bold"]},{"styles":["underline"]}]}"#
+    );
+    let context = crate::context::RenderContext::default();
+    let toml = toml::to_string(&style_sheet).unwrap();
+    for round_trip in [
+        serde_json::from_str::<StyleSheet>(&json).unwrap(),
+        toml::from_str::<StyleSheet>(&toml).unwrap(),
+    ] {
+        for tag in ["error", "path"] {
+            assert_eq!(
+                round_trip.style(&[tag], &context),
+                style_sheet.style(&[tag], &context)
+            );
+        }
+    }
+}
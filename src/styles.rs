@@ -1,4 +1,10 @@
+use crate::declarations::Declaration;
+use crate::palette::{from_hsl, mix};
+use crate::stylesheet_parse::parse_value;
+#[cfg(test)]
 use colored::Colorize;
+use std::fmt;
+use std::str::FromStr;
 
 /// A representation of a terminal color.
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -46,8 +52,145 @@ impl From<Color> for colored::Color {
     }
 }
 
+impl Color {
+    /// The colour's red, green and blue components, using xterm's default palette for the
+    /// named colours.
+    pub(crate) fn rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Color::Black => (0, 0, 0),
+            Color::Red => (205, 0, 0),
+            Color::Green => (0, 205, 0),
+            Color::Yellow => (205, 205, 0),
+            Color::Blue => (0, 0, 238),
+            Color::Magenta => (205, 0, 205),
+            Color::Cyan => (0, 205, 205),
+            Color::White => (229, 229, 229),
+            Color::BrightBlack => (127, 127, 127),
+            Color::BrightRed => (255, 0, 0),
+            Color::BrightGreen => (0, 255, 0),
+            Color::BrightYellow => (255, 255, 0),
+            Color::BrightBlue => (92, 92, 255),
+            Color::BrightMagenta => (255, 0, 255),
+            Color::BrightCyan => (0, 255, 255),
+            Color::BrightWhite => (255, 255, 255),
+            Color::TrueColor { r, g, b } => (r, g, b),
+        }
+    }
+
+    /// The sixteen named colours, in ANSI order.
+    pub(crate) const NAMED: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::White,
+        Color::BrightBlack,
+        Color::BrightRed,
+        Color::BrightGreen,
+        Color::BrightYellow,
+        Color::BrightBlue,
+        Color::BrightMagenta,
+        Color::BrightCyan,
+        Color::BrightWhite,
+    ];
+
+    /// The named colour's ANSI colour number, from 0 to 15.
+    pub(crate) fn ansi_index(&self) -> Option<u8> {
+        Color::NAMED
+            .iter()
+            .position(|color| color == self)
+            .map(|index| index as u8)
+    }
+
+    /// The colour's SGR parameters as a foreground (SGR 30 to 38), background (40 to 48) or
+    /// underline colour (58).
+    pub(crate) fn sgr(&self, layer: Layer) -> String {
+        let Some(index) = self.ansi_index() else {
+            let (r, g, b) = self.rgb();
+            return format!("{};2;{};{};{}", layer as u8 + 8, r, g, b);
+        };
+        match (layer, index) {
+            // The underline colour has no short codes.
+            (Layer::Underline, _) => format!("58;5;{}", index),
+            (_, 0..=7) => (layer as u8 + index).to_string(),
+            _ => (layer as u8 + 60 + index - 8).to_string(),
+        }
+    }
+
+    /// The colour as a CSS hex colour.
+    pub(crate) fn to_css_hex(self) -> String {
+        let (r, g, b) = self.rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+/// What a colour is applied to, as the base of its SGR parameters.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Layer {
+    Foreground = 30,
+    Background = 40,
+    Underline = 50,
+}
+
+/// The colour as written in a stylesheet, e.g. `bright-red` or `#ff8800`.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Color::Black => "black",
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::Magenta => "magenta",
+            Color::Cyan => "cyan",
+            Color::White => "white",
+            Color::BrightBlack => "bright-black",
+            Color::BrightRed => "bright-red",
+            Color::BrightGreen => "bright-green",
+            Color::BrightYellow => "bright-yellow",
+            Color::BrightBlue => "bright-blue",
+            Color::BrightMagenta => "bright-magenta",
+            Color::BrightCyan => "bright-cyan",
+            Color::BrightWhite => "bright-white",
+            Color::TrueColor { .. } => return write!(f, "{}", self.to_css_hex()),
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    /// Parse a colour as written in a stylesheet, e.g. `bright-red` or `#ff8800`.
+    fn from_str(s: &str) -> anyhow::Result<Color> {
+        parse_value(s, "colour", crate::stylesheet_parse::color)
+    }
+}
+
+/// One of the nine alternate fonts, selected with SGR 11 to 19.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+pub struct AlternateFont(u8);
+
+impl AlternateFont {
+    /// Alternate font `number`, if it is 1 to 9.
+    pub fn new(number: u8) -> Option<AlternateFont> {
+        (1..=9).contains(&number).then_some(AlternateFont(number))
+    }
+
+    /// The font's number, 1 to 9.
+    pub fn number(self) -> u8 {
+        self.0
+    }
+}
+
 /// A representation of a terminal style.
+///
+/// New styles may be added in minor releases, so matches on it need a wildcard arm.
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[non_exhaustive]
 pub enum Styles {
     Bold,
     Dimmed,
@@ -57,36 +200,304 @@ pub enum Styles {
     Blink,
     Hidden,
     Strikethrough,
+    RapidBlink,
+    /// An alternate font.
+    Font(AlternateFont),
+    Fraktur,
+    Framed,
+    Encircled,
+    Overline,
+    Superscript,
+    Subscript,
 }
 
 impl Styles {
-    pub(crate) fn apply(&self, s: colored::ColoredString) -> colored::ColoredString {
+    pub(crate) const ALL: [Styles; 24] = [
+        Styles::Bold,
+        Styles::Dimmed,
+        Styles::Underline,
+        Styles::Reversed,
+        Styles::Italic,
+        Styles::Blink,
+        Styles::Hidden,
+        Styles::Strikethrough,
+        Styles::RapidBlink,
+        Styles::Font(AlternateFont(1)),
+        Styles::Font(AlternateFont(2)),
+        Styles::Font(AlternateFont(3)),
+        Styles::Font(AlternateFont(4)),
+        Styles::Font(AlternateFont(5)),
+        Styles::Font(AlternateFont(6)),
+        Styles::Font(AlternateFont(7)),
+        Styles::Font(AlternateFont(8)),
+        Styles::Font(AlternateFont(9)),
+        Styles::Fraktur,
+        Styles::Framed,
+        Styles::Encircled,
+        Styles::Overline,
+        Styles::Superscript,
+        Styles::Subscript,
+    ];
+
+    /// The style's SGR parameter.
+    pub(crate) fn sgr(&self) -> u8 {
         match self {
-            Styles::Bold => s.bold(),
-            Styles::Dimmed => s.dimmed(),
-            Styles::Underline => s.underline(),
-            Styles::Reversed => s.reversed(),
-            Styles::Italic => s.italic(),
-            Styles::Blink => s.blink(),
-            Styles::Hidden => s.hidden(),
-            Styles::Strikethrough => s.strikethrough(),
+            Styles::Bold => 1,
+            Styles::Dimmed => 2,
+            Styles::Italic => 3,
+            Styles::Underline => 4,
+            Styles::Blink => 5,
+            Styles::RapidBlink => 6,
+            Styles::Reversed => 7,
+            Styles::Hidden => 8,
+            Styles::Strikethrough => 9,
+            Styles::Font(font) => 10 + font.0,
+            Styles::Fraktur => 20,
+            Styles::Framed => 51,
+            Styles::Encircled => 52,
+            Styles::Overline => 53,
+            Styles::Superscript => 73,
+            Styles::Subscript => 74,
         }
     }
 }
 
+/// The style as written in a stylesheet's `styles`, e.g. `rapid-blink`.
+impl fmt::Display for Styles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Styles::Bold => "bold",
+            Styles::Dimmed => "dimmed",
+            Styles::Underline => "underline",
+            Styles::Reversed => "reversed",
+            Styles::Italic => "italic",
+            Styles::Blink => "blink",
+            Styles::Hidden => "hidden",
+            Styles::Strikethrough => "strikethrough",
+            Styles::RapidBlink => "rapid-blink",
+            Styles::Font(font) => return write!(f, "font-{}", font.0),
+            Styles::Fraktur => "fraktur",
+            Styles::Framed => "framed",
+            Styles::Encircled => "encircled",
+            Styles::Overline => "overline",
+            Styles::Superscript => "superscript",
+            Styles::Subscript => "subscript",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Styles {
+    type Err = anyhow::Error;
+
+    /// Parse a style as written in a stylesheet's `styles`, e.g. `bold`.
+    fn from_str(s: &str) -> anyhow::Result<Styles> {
+        parse_value(s, "style", crate::stylesheet_parse::styles)
+    }
+}
+
+#[test]
+fn test_from_str() {
+    assert_eq!("bright-red".parse::<Color>().unwrap(), Color::BrightRed);
+    assert_eq!(
+        "#ff8800".parse::<Color>().unwrap(),
+        Color::TrueColor {
+            r: 255,
+            g: 136,
+            b: 0
+        }
+    );
+    assert!("red-ish".parse::<Color>().is_err());
+    assert_eq!(
+        "font-2".parse::<Styles>().unwrap(),
+        Styles::Font(AlternateFont(2))
+    );
+    assert!("font-0".parse::<Styles>().is_err());
+    for style in Styles::ALL {
+        assert_eq!(style.to_string().parse::<Styles>().unwrap(), style);
+    }
+    assert!("bolder".parse::<Styles>().is_err());
+}
+
 #[test]
 fn test_styles() {
-    assert_eq!(Styles::Bold.apply("hello".into()), "hello".bold());
-    assert_eq!(Styles::Dimmed.apply("hello".into()), "hello".dimmed());
-    assert_eq!(Styles::Underline.apply("hello".into()), "hello".underline());
-    assert_eq!(Styles::Reversed.apply("hello".into()), "hello".reversed());
-    assert_eq!(Styles::Italic.apply("hello".into()), "hello".italic());
-    assert_eq!(Styles::Blink.apply("hello".into()), "hello".blink());
-    assert_eq!(Styles::Hidden.apply("hello".into()), "hello".hidden());
+    colored::control::set_override(true);
+    // The original eight styles are written exactly as `colored` writes them.
+    let apply = |style: Styles| Style::new(Some(vec![style]), None, None).to_ansi("hello");
+    assert_eq!(apply(Styles::Bold), "hello".bold().to_string());
+    assert_eq!(apply(Styles::Dimmed), "hello".dimmed().to_string());
+    assert_eq!(apply(Styles::Underline), "hello".underline().to_string());
+    assert_eq!(apply(Styles::Reversed), "hello".reversed().to_string());
+    assert_eq!(apply(Styles::Italic), "hello".italic().to_string());
+    assert_eq!(apply(Styles::Blink), "hello".blink().to_string());
+    assert_eq!(apply(Styles::Hidden), "hello".hidden().to_string());
+    assert_eq!(
+        apply(Styles::Strikethrough),
+        "hello".strikethrough().to_string()
+    );
+    assert_eq!(
+        Style::new(Some(vec![Styles::Overline, Styles::Bold]), None, None).to_ansi("hello"),
+        "hello".bold().to_string().replace("[1m", "[1;53m")
+    );
+    assert_eq!(
+        apply(Styles::Font(AlternateFont(3))),
+        "\x1b[13mhello\x1b[0m"
+    );
+}
+
+/// A CSS-wide keyword that can replace a property's value.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Keyword {
+    /// Use the value from the enclosing tag, ignoring earlier rules (`inherit`).
+    Inherit,
+    /// Reset to the terminal's default, ignoring enclosing tags (`initial`, `default` or `none`).
+    Initial,
+}
+
+/// A change of case applied to text, as in CSS's `text-transform`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum TextTransform {
+    None,
+    Uppercase,
+    Lowercase,
+    /// Uppercase the first letter of each word.
+    Capitalize,
+}
+
+impl TextTransform {
+    /// Transform `text`, which follows the character `previous` (if any) in the rendered output.
+    pub(crate) fn apply(&self, text: &str, mut previous: Option<char>) -> String {
+        match self {
+            TextTransform::None => text.to_string(),
+            TextTransform::Uppercase => text.to_uppercase(),
+            TextTransform::Lowercase => text.to_lowercase(),
+            TextTransform::Capitalize => {
+                let is_word = |c: char| c.is_alphanumeric() || c == '\'' || c == '’';
+                let mut result = String::with_capacity(text.len());
+                for c in text.chars() {
+                    if is_word(c) && !previous.is_some_and(is_word) {
+                        result.extend(c.to_uppercase());
+                    } else {
+                        result.push(c);
+                    }
+                    previous = Some(c);
+                }
+                result
+            }
+        }
+    }
+}
+
+#[test]
+fn test_text_transform() {
+    assert_eq!(TextTransform::Uppercase.apply("straße", None), "STRASSE");
+    assert_eq!(TextTransform::Lowercase.apply("ΑΣ", None), "ας");
+    assert_eq!(
+        TextTransform::Capitalize.apply("don't stop-me ßx", None),
+        "Don't Stop-Me SSx"
+    );
     assert_eq!(
-        Styles::Strikethrough.apply("hello".into()),
-        "hello".strikethrough()
+        TextTransform::Capitalize.apply("lo world", Some('l')),
+        "lo World"
     );
+    assert_eq!(TextTransform::None.apply("MiXed", None), "MiXed");
+}
+
+/// How text is aligned within a tag's `width`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum TextAlign {
+    Left,
+    Right,
+    Center,
+}
+
+/// What happens to text wider than a tag's `width`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Overflow {
+    /// The text is shown in full.
+    Visible,
+    Clip,
+    /// The text is clipped and ends with an ellipsis.
+    Ellipsis,
+}
+
+/// The shape of an underline (SGR 4:1 to 4:5). Terminals that do not support them show a plain
+/// underline, or none.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum UnderlineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    pub(crate) const ALL: [UnderlineStyle; 5] = [
+        UnderlineStyle::Single,
+        UnderlineStyle::Double,
+        UnderlineStyle::Curly,
+        UnderlineStyle::Dotted,
+        UnderlineStyle::Dashed,
+    ];
+
+    pub(crate) fn sgr(&self) -> &'static str {
+        match self {
+            UnderlineStyle::Single => "4:1",
+            UnderlineStyle::Double => "4:2",
+            UnderlineStyle::Curly => "4:3",
+            UnderlineStyle::Dotted => "4:4",
+            UnderlineStyle::Dashed => "4:5",
+        }
+    }
+}
+
+/// A foreground that changes colour across a tag's text, one grapheme at a time.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Gradient {
+    /// Evenly spaced colour stops, from the first grapheme to the last.
+    Linear(Vec<(u8, u8, u8)>),
+    /// Every hue from red to magenta.
+    Rainbow,
+}
+
+impl Gradient {
+    /// The colour at `position`, from 0 at the first grapheme to 1 at the last.
+    pub(crate) fn color_at(&self, position: f32) -> (u8, u8, u8) {
+        match self {
+            Gradient::Linear(stops) => {
+                let Some((last, _)) = stops.split_last() else {
+                    return (0, 0, 0);
+                };
+                let scaled = position.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+                let index = scaled.floor() as usize;
+                match stops.get(index + 1) {
+                    Some(next) => mix(*next, stops[index], scaled - index as f32),
+                    None => *last,
+                }
+            }
+            Gradient::Rainbow => from_hsl((position.clamp(0.0, 1.0) * 300.0, 1.0, 0.5)),
+        }
+    }
+}
+
+#[test]
+fn test_gradient() {
+    let gradient = Gradient::Linear(vec![(255, 0, 0), (0, 0, 255), (0, 0, 0)]);
+    assert_eq!(gradient.color_at(0.0), (255, 0, 0));
+    assert_eq!(gradient.color_at(0.25), (128, 0, 128));
+    assert_eq!(gradient.color_at(0.5), (0, 0, 255));
+    assert_eq!(gradient.color_at(1.0), (0, 0, 0));
+    assert_eq!(Gradient::Rainbow.color_at(0.0), (255, 0, 0));
+    assert_eq!(Gradient::Rainbow.color_at(0.4), (0, 255, 0));
+}
+
+/// Text generated by a `::before` or `::after` pseudo-element.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Content {
+    pub(crate) text: String,
+    /// A replacement for terminals that cannot display Unicode.
+    pub(crate) ascii: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, PartialEq)]
@@ -94,6 +505,28 @@ pub(crate) struct Style {
     pub(crate) styles: Vec<Styles>, // TODO: Hashset
     pub(crate) foreground: Option<Color>,
     pub(crate) background: Option<Color>,
+    /// Replaces the foreground of the tag's text, so it is set or cleared with `foreground`.
+    pub(crate) gradient: Option<Gradient>,
+    /// Styles removed from those set by enclosing tags, e.g. `-bold`.
+    pub(crate) removed_styles: Vec<Styles>,
+    pub(crate) styles_keyword: Option<Keyword>,
+    pub(crate) foreground_keyword: Option<Keyword>,
+    pub(crate) background_keyword: Option<Keyword>,
+    /// Generated content, for pseudo-elements. It is not inherited.
+    pub(crate) content: Option<Content>,
+    pub(crate) text_transform: Option<TextTransform>,
+    /// Implies an underline.
+    pub(crate) underline_style: Option<UnderlineStyle>,
+    pub(crate) underline_color: Option<Color>,
+    /// Raw SGR parameters, e.g. `38;5;208;53`, written after all other styles.
+    pub(crate) sgr: Option<String>,
+    // The box properties below apply to a tag's content as a whole and are not inherited.
+    /// The width of the content, in terminal columns.
+    pub(crate) width: Option<usize>,
+    pub(crate) text_align: Option<TextAlign>,
+    pub(crate) overflow: Option<Overflow>,
+    pub(crate) padding_left: Option<usize>,
+    pub(crate) padding_right: Option<usize>,
 }
 
 impl Style {
@@ -103,40 +536,257 @@ impl Style {
         background: Option<Color>,
     ) -> Style {
         Style {
-            styles: styles.unwrap_or(Vec::new()),
+            styles: styles.unwrap_or_default(),
             foreground,
             background,
+            ..Default::default()
         }
     }
 
+    /// A style that resets everything set by enclosing tags.
+    pub(crate) fn reset() -> Style {
+        Style {
+            styles_keyword: Some(Keyword::Initial),
+            foreground_keyword: Some(Keyword::Initial),
+            background_keyword: Some(Keyword::Initial),
+            text_transform: Some(TextTransform::None),
+            ..Default::default()
+        }
+    }
+
+    /// Combine two styles for the same tag, with `other`'s properties taking precedence.
     pub(crate) fn merge(&self, other: Style) -> Style {
-        let mut styles = self.styles.clone();
-        styles.extend(other.styles);
+        let (mut styles, mut removed_styles) = if other.styles_keyword.is_some() {
+            (Vec::new(), Vec::new())
+        } else {
+            let styles = self
+                .styles
+                .iter()
+                .filter(|s| !other.removed_styles.contains(s));
+            let removed = self
+                .removed_styles
+                .iter()
+                .filter(|s| !other.styles.contains(s));
+            (styles.copied().collect(), removed.copied().collect())
+        };
+        add_styles(&mut styles, &other.styles);
+        add_styles(&mut removed_styles, &other.removed_styles);
+        let (foreground, foreground_keyword, gradient) = if other.foreground.is_some()
+            || other.foreground_keyword.is_some()
+            || other.gradient.is_some()
+        {
+            (other.foreground, other.foreground_keyword, other.gradient)
+        } else {
+            (
+                self.foreground,
+                self.foreground_keyword,
+                self.gradient.clone(),
+            )
+        };
+        let (background, background_keyword) =
+            if other.background.is_some() || other.background_keyword.is_some() {
+                (other.background, other.background_keyword)
+            } else {
+                (self.background, self.background_keyword)
+            };
         Style {
             styles,
-            foreground: other.foreground.or(self.foreground),
-            background: other.background.or(self.background),
+            foreground,
+            background,
+            gradient,
+            removed_styles,
+            styles_keyword: other.styles_keyword.or(self.styles_keyword),
+            foreground_keyword,
+            background_keyword,
+            content: other.content.or_else(|| self.content.clone()),
+            text_transform: other.text_transform.or(self.text_transform),
+            underline_style: other.underline_style.or(self.underline_style),
+            underline_color: other.underline_color.or(self.underline_color),
+            sgr: other.sgr.or_else(|| self.sgr.clone()),
+            width: other.width.or(self.width),
+            text_align: other.text_align.or(self.text_align),
+            overflow: other.overflow.or(self.overflow),
+            padding_left: other.padding_left.or(self.padding_left),
+            padding_right: other.padding_right.or(self.padding_right),
         }
     }
 
+    /// Apply the style to `text` as ANSI escape codes, unless `colored` has been told not to
+    /// colourize (e.g. by `NO_COLOR`).
+    pub(crate) fn to_ansi(&self, text: &str) -> String {
+        if !colored::control::SHOULD_COLORIZE.should_colorize() {
+            return text.to_string();
+        }
+        let mut styles: Vec<u8> = self.styles.iter().map(Styles::sgr).collect();
+        styles.sort();
+        let mut codes: Vec<String> = styles.iter().map(u8::to_string).collect();
+        if let Some(color) = self.background {
+            codes.push(color.sgr(Layer::Background));
+        }
+        if let Some(color) = self.foreground {
+            codes.push(color.sgr(Layer::Foreground));
+        }
+        if let Some(underline_style) = self.underline_style {
+            codes.push(underline_style.sgr().to_string());
+        }
+        if let Some(color) = self.underline_color {
+            codes.push(color.sgr(Layer::Underline));
+        }
+        if let Some(sgr) = &self.sgr {
+            codes.push(sgr.clone());
+        }
+        if codes.is_empty() {
+            return text.to_string();
+        }
+        format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+    }
+
+    /// The style of text nested inside tags with the styles in `stack`, outermost first.
     pub(crate) fn resolve(stack: &Vec<Style>) -> Style {
         let mut styles: Vec<Styles> = Vec::new();
         let mut foreground: Option<Color> = None;
+        let mut gradient: Option<Gradient> = None;
         let mut background: Option<Color> = None;
+        let mut text_transform: Option<TextTransform> = None;
+        let mut underline_style: Option<UnderlineStyle> = None;
+        let mut underline_color: Option<Color> = None;
+        let mut sgr: Option<String> = None;
 
         for style in stack {
-            styles.extend(style.styles.iter());
-            if style.foreground.is_some() {
-                foreground = style.foreground;
+            if style.styles_keyword == Some(Keyword::Initial) {
+                styles.clear();
+            }
+            styles.retain(|s| !style.removed_styles.contains(s));
+            add_styles(&mut styles, &style.styles);
+            if style.gradient.is_some() {
+                (foreground, gradient) = (None, style.gradient.clone());
+            } else if style.foreground.is_some() {
+                (foreground, gradient) = (style.foreground, None);
+            } else if style.foreground_keyword == Some(Keyword::Initial) {
+                (foreground, gradient) = (None, None);
             }
             if style.background.is_some() {
                 background = style.background;
+            } else if style.background_keyword == Some(Keyword::Initial) {
+                background = None;
+            }
+            if style.text_transform.is_some() {
+                text_transform = style.text_transform;
+            }
+            if style.styles_keyword == Some(Keyword::Initial) {
+                (underline_style, underline_color, sgr) = (None, None, None);
             }
+            sgr = style.sgr.clone().or(sgr);
+            underline_style = style.underline_style.or(underline_style);
+            underline_color = style.underline_color.or(underline_color);
         }
         Style {
             styles,
             foreground,
+            gradient,
             background,
+            text_transform,
+            underline_style,
+            underline_color,
+            sgr,
+            ..Default::default()
         }
     }
 }
+
+/// The style's declarations, separated by semicolons, e.g. `styles: bold; foreground: red`.
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let declarations: Vec<String> = Declaration::from_style(self)
+            .iter()
+            .map(Declaration::to_string)
+            .collect();
+        write!(f, "{}", declarations.join("; "))
+    }
+}
+
+/// Append the styles not already present.
+fn add_styles(styles: &mut Vec<Styles>, new: &[Styles]) {
+    for style in new {
+        if !styles.contains(style) {
+            styles.push(*style);
+        }
+    }
+}
+
+#[test]
+fn test_resolve() {
+    let bold_red = Style::new(Some(vec![Styles::Bold]), Some(Color::Red), None);
+    let dim = Style::new(Some(vec![Styles::Dimmed]), None, None);
+    assert_eq!(
+        Style::resolve(&vec![bold_red.clone(), dim.clone()]),
+        Style::new(
+            Some(vec![Styles::Bold, Styles::Dimmed]),
+            Some(Color::Red),
+            None
+        )
+    );
+
+    let not_bold = Style {
+        removed_styles: vec![Styles::Bold],
+        foreground_keyword: Some(Keyword::Initial),
+        ..dim.clone()
+    };
+    assert_eq!(
+        Style::resolve(&vec![bold_red.clone(), not_bold]),
+        Style::new(Some(vec![Styles::Dimmed]), None, None)
+    );
+
+    assert_eq!(
+        Style::resolve(&vec![bold_red.clone(), Style::reset().merge(dim.clone())]),
+        Style {
+            text_transform: Some(TextTransform::None),
+            ..dim
+        }
+    );
+}
+
+#[test]
+fn test_underline() {
+    colored::control::set_override(true);
+    let style = Style {
+        underline_style: Some(UnderlineStyle::Curly),
+        underline_color: Some(Color::TrueColor { r: 255, g: 0, b: 0 }),
+        ..Default::default()
+    };
+    assert_eq!(style.to_ansi("oops"), "\x1b[4:3;58;2;255;0;0moops\x1b[0m");
+    let style = Style {
+        foreground: Some(Color::Red),
+        underline_color: Some(Color::BrightRed),
+        ..Default::default()
+    };
+    assert_eq!(style.to_ansi("oops"), "\x1b[31;58;5;9moops\x1b[0m");
+}
+
+#[test]
+fn test_merge_keywords() {
+    let bold_red = Style::new(Some(vec![Styles::Bold]), Some(Color::Red), None);
+    let inherit = Style {
+        foreground_keyword: Some(Keyword::Inherit),
+        styles_keyword: Some(Keyword::Inherit),
+        ..Default::default()
+    };
+    let merged = bold_red.merge(inherit.clone());
+    assert_eq!(merged, inherit);
+    assert_eq!(
+        Style::resolve(&vec![
+            Style::new(Some(vec![Styles::Italic]), Some(Color::Blue), None),
+            merged
+        ]),
+        Style::new(Some(vec![Styles::Italic]), Some(Color::Blue), None)
+    );
+
+    let not_bold = Style {
+        removed_styles: vec![Styles::Bold],
+        ..Default::default()
+    };
+    let merged = bold_red.merge(not_bold);
+    assert_eq!(merged.styles, vec![]);
+    assert_eq!(merged.removed_styles, vec![Styles::Bold]);
+    assert_eq!(merged.foreground, Some(Color::Red));
+}
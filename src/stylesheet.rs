@@ -1,41 +1,195 @@
-use anyhow::{anyhow, Ok, Result};
-use colored::ColoredString;
-use colored::Colorize;
+use anyhow::{anyhow, Context, Ok, Result};
 use std::collections::HashMap;
+use std::fmt;
 
+use crate::context::*;
+use crate::declarations::*;
+use crate::layout::*;
 use crate::markup::*;
+use crate::palette::*;
+use crate::resolver::*;
+use crate::selector::*;
 use crate::styles::*;
-use crate::stylesheet_parse::parse;
+use crate::stylesheet_parse::{parse, Item};
+use std::borrow::Cow;
+
+/// Add a span, applying its `text-transform`.
+fn push_span<'m>(spans: &mut Spans<'m>, text: Cow<'m, str>, style: Style) {
+    let text = match style.text_transform {
+        Some(text_transform) => {
+            let previous = spans.iter().rev().find_map(|(text, _)| text.chars().last());
+            text_transform.apply(&text, previous).into()
+        }
+        None => text,
+    };
+    spans.push((text, style));
+}
+
+/// The built-in tag that clears every style set by enclosing tags.
+const RESET_TAG: &str = "reset";
 
 /// A struct representing styles to apply to markup.
-#[derive(Debug, PartialEq)]
+///
+/// A stylesheet parsed with [`StyleSheet::parse`] borrows from its source string. Use
+/// [`StyleSheet::parse_owned`] or [`StyleSheet::into_owned`] for a `StyleSheet<'static>` that
+/// can be kept in a `static` or a long-lived struct.
+#[derive(Clone, Debug, PartialEq)]
 pub struct StyleSheet<'a> {
-    styles: HashMap<&'a str, Style>,
-}
-
-// impl<'a> Default for StyleSheet<'a> {
-//     fn default() -> StyleSheet<'a> {
-//         let styles = vec![
-//             ("bold", Style::new(Some(vec![Styles::Bold]), None, None)),
-//             ("em", Style::new(Some(vec![Styles::Italic]), None, None)),
-//             (
-//                 "strikethrough",
-//                 Style::new(Some(vec![Styles::Strikethrough]), None, None),
-//             ),
-//         ];
-//         StyleSheet::new_internal(&styles)
-//     }
-// }
+    pub(crate) rules: Vec<Rule<'a>>,
+    pub(crate) variables: HashMap<String, ColorExpr>,
+    palette: Palette,
+}
+
+/// A single `selector { ... }` rule, in source order. Rules with a list of selectors are split
+/// into one rule per selector.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Rule<'a> {
+    pub(crate) selector: Selector<'a>,
+    pub(crate) declarations: Vec<Declaration>,
+    /// The `@media` query the rule is nested in, if any.
+    pub(crate) media: Option<MediaQuery>,
+    /// The normal declarations with all variables substituted.
+    pub(crate) style: Style,
+    /// The `!important` declarations with all variables substituted.
+    pub(crate) important_style: Style,
+}
+
+impl<'a> Rule<'a> {
+    fn into_owned(self) -> Rule<'static> {
+        Rule {
+            selector: self.selector.into_owned(),
+            declarations: self.declarations,
+            media: self.media,
+            style: self.style,
+            important_style: self.important_style,
+        }
+    }
+
+    fn new(
+        selector: Selector<'a>,
+        declarations: Vec<Declaration>,
+        media: Option<MediaQuery>,
+    ) -> Rule<'a> {
+        Rule {
+            selector,
+            declarations,
+            media,
+            style: Style::default(),
+            important_style: Style::default(),
+        }
+    }
+
+    /// Whether the rule applies to the innermost of `tags` (outermost first), or one of its
+    /// pseudo-elements, in `context`.
+    pub(crate) fn applies_to(
+        &self,
+        tags: &[&str],
+        pseudo_element: Option<PseudoElement>,
+        context: &RenderContext,
+    ) -> bool {
+        self.selector.pseudo_element == pseudo_element
+            && self.selector.matches(tags)
+            && self
+                .media
+                .as_ref()
+                .is_none_or(|media| media.matches(context))
+    }
+}
+
+/// The source of the default stylesheet, [`StyleSheet::default`], which styles a standard set
+/// of tags using only the sixteen named colours:
+///
+/// | Tags | Used for |
+/// |------|----------|
+/// | `b`, `strong` | Bold text |
+/// | `i`, `em` | Italic text |
+/// | `u` | Underlined text |
+/// | `s` | Struck-through text |
+/// | `dim` | Less important text |
+/// | `code` | Code and commands |
+/// | `kbd` | Keys to press |
+/// | `path`, `url` | Paths and links |
+/// | `error`, `warn`, `info`, `success`, `hint` | Messages |
+/// | `num`, `key`, `value` | Numbers and key-value pairs |
+pub const DEFAULT_STYLE_SHEET: &str = "\
+b, strong { styles: bold }
+i, em { styles: italic }
+u { styles: underline }
+s { styles: strikethrough }
+dim { styles: dimmed }
+code { foreground: yellow }
+kbd { styles: reversed; padding: 0 1 }
+path { foreground: cyan }
+url { foreground: blue; styles: underline }
+error { foreground: bright-red; styles: bold }
+warn { foreground: yellow; styles: bold }
+info { foreground: bright-blue }
+success { foreground: green; styles: bold }
+hint { styles: dimmed italic }
+num { foreground: magenta }
+key { styles: bold }
+value { foreground: green }
+";
+
+/// The stylesheet in [`DEFAULT_STYLE_SHEET`]. An application's own rules can be layered on
+/// top, overriding only what they set:
+///
+/// ```rust
+/// use colored_markup::StyleSheet;
+///
+/// let style_sheet = StyleSheet::default()
+///     .with_overrides("error { foreground: red } badge { styles: reversed }")
+///     .unwrap();
+/// println!("{}", style_sheet.render("<error>Failed</error> to open <path>x</path>").unwrap());
+/// ```
+impl Default for StyleSheet<'_> {
+    fn default() -> Self {
+        StyleSheet::parse(DEFAULT_STYLE_SHEET).expect("The default stylesheet is valid")
+    }
+}
+
+#[test]
+fn test_stylesheet_default() {
+    colored::control::set_override(true);
+    let style_sheet = StyleSheet::default();
+    let context = RenderContext::default();
+    let tags = [
+        "b", "strong", "i", "em", "u", "s", "dim", "code", "kbd", "path", "url", "error", "warn",
+        "info", "success", "hint", "num", "key", "value",
+    ];
+    for tag in tags {
+        assert_ne!(
+            style_sheet.style(&[tag], &context),
+            Style::default(),
+            "{}",
+            tag
+        );
+    }
+    assert_eq!(
+        style_sheet.render("<kbd>q</kbd>").unwrap(),
+        "\u{1b}[7m \u{1b}[0m\u{1b}[7mq\u{1b}[0m\u{1b}[7m \u{1b}[0m"
+    );
+    let style_sheet = StyleSheet::default()
+        .with_overrides("error { foreground: red }")
+        .unwrap();
+    assert_eq!(
+        style_sheet.style(&["error"], &context),
+        Style::new(Some(vec![Styles::Bold]), Some(Color::Red), None)
+    );
+}
 
 impl<'a> StyleSheet<'a> {
     /// Create a new `StyleSheet` from a list of style names, styles, foreground and background colors.
+    #[allow(clippy::type_complexity)]
     pub fn new(styles: &[(&'a str, Vec<Styles>, Option<Color>, Option<Color>)]) -> StyleSheet<'a> {
-        let styles = styles.iter().map(|(name, styles, foreground, background)| {
-            let style = Style::new(Some(styles.clone()), *foreground, *background);
-            (*name, style)
-        });
-        let styles = HashMap::from_iter(styles);
-        StyleSheet { styles }
+        let styles: Vec<_> = styles
+            .iter()
+            .map(|(name, styles, foreground, background)| {
+                let style = Style::new(Some(styles.clone()), *foreground, *background);
+                (*name, style)
+            })
+            .collect();
+        StyleSheet::new_internal(&styles)
     }
 }
 
@@ -48,16 +202,332 @@ fn test_stylesheet_new() {
 
 impl<'a> StyleSheet<'a> {
     pub(crate) fn new_internal(styles: &[(&'a str, Style)]) -> StyleSheet<'a> {
-        let styles = HashMap::from_iter(styles.iter().cloned());
-        StyleSheet { styles }
+        let rules = styles
+            .iter()
+            .map(|(name, style)| Rule {
+                selector: Selector::tag(*name),
+                declarations: Declaration::from_style(style),
+                media: None,
+                style: style.clone(),
+                important_style: Style::default(),
+            })
+            .collect();
+        StyleSheet {
+            rules,
+            variables: HashMap::new(),
+            palette: Palette::default(),
+        }
     }
 }
 
 impl<'a> StyleSheet<'a> {
     /// Parse a stylesheet from a string.
+    ///
+    /// Selectors may name a tag, a tag nested anywhere inside others (`error path`) or any tag
+    /// (`*`), and may be listed together (`error, warning`). Rules cascade as in CSS: several
+    /// rules can apply to a tag and are combined property by property, more specific selectors
+    /// and then later rules winning, with `!important` declarations winning over the rest.
+    ///
+    /// Colours may be defined once as variables in a `:root` block and referred to with `var()`,
+    /// optionally with a fallback:
+    ///
+    /// ```css
+    /// :root { --accent: #ff8800 }
+    /// title { foreground: var(--accent); styles: bold }
+    /// link { foreground: var(--link, cyan) }
+    /// ```
+    ///
+    /// The CSS properties `color`, `background-color`, `font-weight`, `font-style`,
+    /// `text-decoration`, `text-decoration-style`, `text-decoration-color` and `opacity` are
+    /// accepted too, so simple CSS works unchanged:
+    ///
+    /// ```css
+    /// a { color: blue; text-decoration: underline }
+    /// strong { font-weight: bold }
+    /// muted { opacity: 0.5 }
+    /// ```
+    ///
+    /// Colours can be computed with `lighten(var(--accent), 20%)`, `darken(red, 10%)`,
+    /// `saturate()`, `desaturate()`, `alpha(red, 50%)` (blended with the background) and
+    /// `mix(#ff0000, blue, 30%)` (30% of the first colour). The results are true colours, with
+    /// the named colours taken from the stylesheet's [`Palette`].
+    ///
+    /// `palette(red)`, `palette(background)` or a base16 name such as `base08` is the palette's
+    /// true colour for that name, so a palette imported from the user's terminal theme (see
+    /// [`Palette::from_base16`]) gives colours that match it.
+    ///
+    /// `foreground: linear-gradient(#ff0000, #0000ff)` or `foreground: rainbow` colours a tag's
+    /// text one character at a time, using the nearest of the 256 colours or of the named
+    /// colours on terminals without true colour. Nested tags that set their own foreground keep
+    /// it.
+    ///
+    /// Besides colours, `foreground` and `background` accept `default` (or `initial`) for the
+    /// terminal's default colour and `inherit` for the enclosing tag's colour. `styles` accepts
+    /// `none` to clear the enclosing tags' styles and `-bold` or `no-bold` to remove one of
+    /// them. The built-in `<reset>` tag clears everything set by enclosing tags, and can be
+    /// styled like any other tag.
+    ///
+    /// A `content: "text"` declaration on an `error::before` or `error::after` rule inserts
+    /// styled text before or after the content of each `<error>` tag. A fallback for terminals
+    /// without Unicode can follow a slash: `content: "⚠ " / "! "`.
+    ///
+    /// `text-transform: uppercase`, `lowercase`, `capitalize` or `none` changes the case of a
+    /// tag's text when it is rendered.
+    ///
+    /// Besides `bold`, `dimmed`, `underline`, `reversed`, `italic`, `blink`, `hidden` and
+    /// `strikethrough`, `styles` accepts `rapid-blink`, `overline`, `framed`, `encircled`,
+    /// `superscript`, `subscript`, `fraktur` and the alternate fonts `font-1` to `font-9`. Many
+    /// terminals ignore these. `sgr: "38;5;208;53"` writes raw SGR parameters for anything else.
+    ///
+    /// `underline-style: single|double|curly|dotted|dashed` and `underline-color: red` draw
+    /// the underline used by editors for diagnostics. Terminals that do not support them ignore
+    /// them, or show a plain underline.
+    ///
+    /// `width: 8` lays a tag's content out in a fixed number of columns, padding it according
+    /// to `text-align: left|right|center` and, if it is too wide, clipping it according to
+    /// `overflow: visible|clip|ellipsis`. `padding: 0 1` (or `padding-left` and `padding-right`)
+    /// adds spaces on either side. Widths are measured in terminal columns, so escape codes
+    /// and wide characters are accounted for.
+    ///
+    /// Rules inside an `@media` block only apply when every condition matches the
+    /// [`RenderContext`] the markup is rendered with. The supported conditions are
+    /// `(colors >= 256)` (also `<`, `<=`, `=`, `>`), `(prefers-color-scheme: light|dark)`,
+    /// `(env: NAME)` and `(output: ansi|plain|html)`:
+    ///
+    /// ```css
+    /// error { foreground: red }
+    /// @media (colors >= 256) and (prefers-color-scheme: dark) { error { foreground: #ff5f5f } }
+    /// @media (env: CI) { error { styles: bold } }
+    /// ```
+    ///
+    /// An unknown property or invalid value is an error naming the declaration and its line.
     pub fn parse(s: &'a str) -> Result<StyleSheet<'a>> {
-        let rules = parse(s)?;
-        Ok(StyleSheet::new_internal(&rules))
+        StyleSheet::parse_internal(s, None)
+    }
+
+    /// A copy of the stylesheet that does not borrow from its source.
+    pub fn into_owned(self) -> StyleSheet<'static> {
+        let rules = self.rules.into_iter().map(Rule::into_owned).collect();
+        StyleSheet {
+            rules,
+            variables: self.variables,
+            palette: self.palette,
+        }
+    }
+
+    /// Parse a stylesheet from a string, loading any `@import "path";` rules with `resolver`.
+    ///
+    /// Imported rules are added where the `@import` appears, so rules cascade in import order
+    /// and the importing stylesheet's own rules come after those it imports.
+    pub fn parse_with_resolver(
+        s: &'a str,
+        resolver: &dyn StyleSheetResolver,
+    ) -> Result<StyleSheet<'a>> {
+        StyleSheet::parse_internal(s, Some(resolver))
+    }
+
+    fn parse_internal(
+        s: &'a str,
+        resolver: Option<&dyn StyleSheetResolver>,
+    ) -> Result<StyleSheet<'a>> {
+        let mut style_sheet = StyleSheet {
+            rules: Vec::new(),
+            variables: HashMap::new(),
+            palette: Palette::default(),
+        };
+        style_sheet.add_items(parse(s)?, resolver, &mut Vec::new())?;
+        style_sheet.resolve()?;
+        Ok(style_sheet)
+    }
+
+    /// Add parsed items to the stylesheet, recursively loading imports. `importers` holds the
+    /// ids of the stylesheets currently being imported, innermost last.
+    fn add_items(
+        &mut self,
+        items: Vec<Item<'a>>,
+        resolver: Option<&dyn StyleSheetResolver>,
+        importers: &mut Vec<String>,
+    ) -> Result<()> {
+        for item in items {
+            match item {
+                Item::Rule(selectors, declarations) => {
+                    self.add_rules(selectors, declarations, None)
+                }
+                Item::Media(query, rules) => {
+                    for (selectors, declarations) in rules {
+                        self.add_rules(selectors, declarations, Some(&query));
+                    }
+                }
+                Item::Variables(definitions) => self.variables.extend(definitions),
+                Item::Import(path) => {
+                    let resolver = resolver
+                        .ok_or_else(|| anyhow!("Cannot import {} without a resolver", path))?;
+                    let import = resolver.resolve(&path, importers.last().map(String::as_str))?;
+                    if importers.contains(&import.id) {
+                        return Err(anyhow!("Import cycle involving {}", import.id));
+                    }
+                    let items = parse(&import.source)
+                        .with_context(|| format!("Failed to parse {}", import.id))?
+                        .into_iter()
+                        .map(Item::into_owned)
+                        .collect();
+                    importers.push(import.id);
+                    self.add_items(items, Some(resolver), importers)?;
+                    importers.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the rules and variables of `other` after this stylesheet's own, as if its source
+    /// followed this one's. Rules combine property by property, so `other` only overrides the
+    /// properties it sets, for selectors at least as specific. Its variables replace any of the
+    /// same name, including where this stylesheet's rules refer to them.
+    /// On error, e.g. a variable cycle, the stylesheet is left unchanged.
+    pub fn extend(&mut self, other: StyleSheet<'a>) -> Result<()> {
+        let mut extended = self.clone();
+        extended.rules.extend(other.rules);
+        extended.variables.extend(other.variables);
+        extended.resolve()?;
+        *self = extended;
+        Ok(())
+    }
+
+    /// Combine stylesheets, each layered over the ones before it, e.g. an application's defaults,
+    /// then the user's configuration, then command line overrides. The palette is the first
+    /// stylesheet's.
+    pub fn layered(
+        style_sheets: impl IntoIterator<Item = StyleSheet<'a>>,
+    ) -> Result<StyleSheet<'a>> {
+        let mut style_sheets = style_sheets.into_iter();
+        let mut result = style_sheets.next().unwrap_or_else(|| StyleSheet::new(&[]));
+        for style_sheet in style_sheets {
+            result.extend(style_sheet)?;
+        }
+        Ok(result)
+    }
+
+    /// Layer the overrides in the environment variable `name`, if set, over the stylesheet, so
+    /// users can adjust an application's colours without a configuration file. See
+    /// [`StyleSheet::with_overrides`] for the format.
+    pub fn with_env_overrides(self, name: &str) -> Result<StyleSheet<'a>> {
+        let Some(overrides) = std::env::var(name).ok() else {
+            return Ok(self);
+        };
+        self.with_overrides(&overrides)
+            .with_context(|| format!("Invalid {}", name))
+    }
+
+    /// Layer overrides over the stylesheet, as with [`StyleSheet::extend`].
+    ///
+    /// The overrides are either a stylesheet or, in the tradition of `LS_COLORS` and
+    /// `GREP_COLORS`, a list of selectors and SGR parameters separated by colons, e.g.
+    /// `error=1;31:path=4;36`. The parameters are converted to the equivalent declarations, so
+    /// `1;31` is `styles: bold; foreground: red`, and combine with the stylesheet's like any
+    /// other rule.
+    pub fn with_overrides(mut self, overrides: &str) -> Result<StyleSheet<'a>> {
+        let overrides = if overrides.contains('{') {
+            StyleSheet::parse_owned(overrides)?
+        } else {
+            StyleSheet::from_sgr(parse_assignments(overrides)?)?
+        };
+        self.extend(overrides)?;
+        Ok(self)
+    }
+
+    /// Set (or override) the value of the variable `--name`, updating every rule that refers to it.
+    pub fn set_variable(&mut self, name: &str, color: Color) {
+        let name = name.trim_start_matches("--");
+        self.variables
+            .insert(name.to_string(), ColorExpr::Color(color));
+        // Defining a variable as a concrete colour can only make more references resolvable.
+        self.resolve()
+            .expect("Stylesheet was resolvable before setting a variable");
+    }
+
+    /// Get the current value of the variable `--name`.
+    pub fn variable(&self, name: &str) -> Option<Color> {
+        let name = name.trim_start_matches("--");
+        self.variables
+            .get(name)?
+            .resolve(&self.variables, &self.palette)
+            .ok()
+    }
+
+    /// Set the palette colour functions compute with, updating every rule.
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        // The palette only changes colour values, which cannot make a rule unresolvable.
+        self.resolve()
+            .expect("Stylesheet was resolvable before setting the palette");
+    }
+
+    /// The palette colour functions compute with.
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    /// Add a rule with `declarations` for each of `selectors`, only applying where `media`
+    /// matches if it is given.
+    fn add_rules(
+        &mut self,
+        selectors: Vec<Selector<'a>>,
+        declarations: Vec<Declaration>,
+        media: Option<&MediaQuery>,
+    ) {
+        for selector in selectors {
+            let rule = Rule::new(selector, declarations.clone(), media.cloned());
+            self.rules.push(rule);
+        }
+    }
+
+    /// Resolve each rule's declarations into its normal and `!important` styles, substituting
+    /// variables and palette colours.
+    pub(crate) fn resolve(&mut self) -> Result<()> {
+        let (variables, palette) = (&self.variables, &self.palette);
+        for rule in &mut self.rules {
+            rule.style = resolve_declarations(&rule.declarations, false, variables, palette)?;
+            rule.important_style =
+                resolve_declarations(&rule.declarations, true, variables, palette)?;
+        }
+        Ok(())
+    }
+
+    /// The cascaded style for the innermost of `tags` (outermost first).
+    ///
+    /// Every rule that applies in `context` contributes, property by property: rules with more
+    /// specific selectors win over less specific ones, and among equally specific rules later
+    /// ones win. `!important` declarations win over all normal declarations.
+    pub(crate) fn style(&self, tags: &[&str], context: &RenderContext) -> Style {
+        self.cascade(tags, None, context)
+    }
+
+    /// The cascaded style for the innermost of `tags` or, if given, one of its pseudo-elements.
+    fn cascade(
+        &self,
+        tags: &[&str],
+        pseudo_element: Option<PseudoElement>,
+        context: &RenderContext,
+    ) -> Style {
+        let mut rules: Vec<&Rule> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.applies_to(tags, pseudo_element, context))
+            .collect();
+        // A stable sort, so source order is kept for equal specificity.
+        rules.sort_by_key(|rule| rule.selector.specificity());
+        let initial = if pseudo_element.is_none() && tags.last() == Some(&RESET_TAG) {
+            Style::reset()
+        } else {
+            Style::default()
+        };
+        let style = rules
+            .iter()
+            .fold(initial, |style, rule| style.merge(rule.style.clone()));
+        rules.iter().fold(style, |style, rule| {
+            style.merge(rule.important_style.clone())
+        })
     }
 }
 
@@ -72,63 +542,735 @@ fn test_stylesheet() {
 }
 
 impl<'a> StyleSheet<'a> {
-    pub(crate) fn render_to_colored_string(&self, markup: &str) -> Result<Vec<ColoredString>> {
+    /// Split markup into runs of text and the style each should be rendered with.
+    pub(crate) fn render_spans<'m>(
+        &self,
+        markup: &'m str,
+        context: &RenderContext,
+    ) -> Result<Spans<'m>> {
         let parts = Markup::parse(markup)?.parts;
+        let mut tag_stack: Vec<&str> = Vec::new();
         let mut style_stack: Vec<Style> = Vec::new();
-        let mut colored_strings: Vec<colored::ColoredString> = Vec::new();
+        // The index of the first span of each open tag's content.
+        let mut start_stack: Vec<usize> = Vec::new();
+        let mut spans = Vec::new();
         for part in parts {
             match part {
                 Part::Text(text) => {
-                    let style = Style::resolve(&style_stack);
-                    let mut text = ColoredString::from(text);
-                    for style in style.styles {
-                        text = style.apply(text);
-                    }
-                    if let Some(color) = style.foreground {
-                        text = text.color(color);
-                    }
-                    if let Some(color) = style.background {
-                        text = text.on_color(color);
-                    }
-                    colored_strings.push(text);
+                    push_span(&mut spans, text.into(), Style::resolve(&style_stack))
                 }
                 Part::OpenTag(tag) => {
-                    if let Some(style) = self.styles.get(tag) {
-                        style_stack.push(style.clone());
-                    } else {
-                        style_stack.push(Style::default());
+                    tag_stack.push(tag);
+                    style_stack.push(self.style(&tag_stack, context));
+                    start_stack.push(spans.len());
+                    let before = PseudoElement::Before;
+                    if let Some((text, style)) =
+                        self.generated_content(&tag_stack, &style_stack, before, context)
+                    {
+                        push_span(&mut spans, text, style);
                     }
                 }
                 Part::CloseTag(_) => {
-                    style_stack
-                        .pop()
-                        .ok_or_else(|| anyhow!("Invalid template"))?;
+                    let Some(start) = start_stack.pop() else {
+                        return Err(anyhow!("Invalid template"));
+                    };
+                    let after = PseudoElement::After;
+                    if let Some((text, style)) =
+                        self.generated_content(&tag_stack, &style_stack, after, context)
+                    {
+                        push_span(&mut spans, text, style);
+                    }
+                    // Padding is not part of the tag's text, so no gradient colours it.
+                    let fill = Style {
+                        gradient: None,
+                        ..Style::resolve(&style_stack)
+                    };
+                    let style = style_stack.pop().unwrap();
+                    let mut content = spans.split_off(start);
+                    if let Some(gradient) = &style.gradient {
+                        apply_gradient(&mut content, gradient, context, &self.palette);
+                    }
+                    layout(&mut content, &style, &fill, context);
+                    spans.extend(content);
+                    tag_stack.pop();
                 }
             }
         }
-        Ok(colored_strings)
+        Ok(spans)
+    }
+
+    /// The span for the `content` of a tag's `::before` or `::after` pseudo-element, if any.
+    fn generated_content<'m>(
+        &self,
+        tags: &[&str],
+        style_stack: &[Style],
+        pseudo_element: PseudoElement,
+        context: &RenderContext,
+    ) -> Option<(Cow<'m, str>, Style)> {
+        let style = self.cascade(tags, Some(pseudo_element), context);
+        let content = style.content.clone()?;
+        let text = match content.ascii {
+            Some(ascii) if !context.unicode => ascii,
+            _ => content.text,
+        };
+        let mut stack = style_stack.to_vec();
+        stack.push(style);
+        Some((text.into(), Style::resolve(&stack)))
     }
 
+    /// Render markup for the context detected from the environment, including
+    /// [`colored::control::set_override`], at the time of the call.
+    ///
+    /// Tags are a lowercase letter followed by lowercase letters, digits, `-` or `_`, such as
+    /// `<ext-rs>`. Anything else in angle brackets is left as text.
     pub fn render(&self, markup: &str) -> Result<String> {
-        let colored_strings = self.render_to_colored_string(markup)?;
+        self.render_with_context(markup, &RenderContext::detect())
+    }
+
+    /// Render markup for an explicit context, which `@media` rules are evaluated against.
+    pub fn render_with_context(&self, markup: &str, context: &RenderContext) -> Result<String> {
         let mut result = String::new();
-        for colored_string in colored_strings {
-            let f = format!("{}", colored_string);
-            result.push_str(&f);
+        for (text, style) in self.render_spans(markup, context)? {
+            match context.output {
+                Output::Ansi => result.push_str(&style.to_ansi(&text)),
+                Output::Plain | Output::Html => result.push_str(&text),
+            }
         }
         Ok(result)
     }
 }
 
+impl StyleSheet<'static> {
+    /// Parse a stylesheet that does not borrow from `s`, e.g. one read from a file at runtime.
+    pub fn parse_owned(s: impl AsRef<str>) -> Result<StyleSheet<'static>> {
+        Ok(StyleSheet::parse(s.as_ref())?.into_owned())
+    }
+
+    /// Load a stylesheet from a file, with any `@import`s relative to it. See
+    /// [`WatchedStyleSheet`](crate::watch::WatchedStyleSheet) to reload it when it changes.
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Result<StyleSheet<'static>> {
+        Ok(crate::watch::load(path.as_ref())?.0)
+    }
+
+    /// A stylesheet with a rule for each selector, declaring the equivalent of its SGR
+    /// parameters.
+    pub(crate) fn from_sgr<'s>(
+        rules: impl IntoIterator<Item = (impl AsRef<str>, &'s str)>,
+    ) -> Result<StyleSheet<'static>> {
+        let mut source = String::new();
+        for (selector, sgr) in rules {
+            let declarations: Vec<String> = Declaration::from_sgr(sgr)?
+                .iter()
+                .map(|declaration| declaration.to_string())
+                .collect();
+            let selector = selector.as_ref();
+            source.push_str(&format!("{} {{ {} }}\n", selector, declarations.join("; ")));
+        }
+        StyleSheet::parse_owned(source)
+    }
+
+    /// Parse a stylesheet that does not borrow from `s`, loading imports with `resolver`.
+    pub fn parse_owned_with_resolver(
+        s: impl AsRef<str>,
+        resolver: &dyn StyleSheetResolver,
+    ) -> Result<StyleSheet<'static>> {
+        Ok(StyleSheet::parse_with_resolver(s.as_ref(), resolver)?.into_owned())
+    }
+}
+
+#[test]
+fn test_stylesheet_owned() {
+    colored::control::set_override(true);
+    use std::sync::OnceLock;
+    static THEME: OnceLock<StyleSheet<'static>> = OnceLock::new();
+    fn assert_send_sync<T: Send + Sync + Clone>(_: &T) {}
+
+    let source = String::from("error { foreground: red }");
+    let theme = THEME.get_or_init(|| StyleSheet::parse_owned(source).unwrap());
+    assert_send_sync(theme);
+    assert_eq!(
+        theme.clone(),
+        StyleSheet::parse("error { foreground: red }").unwrap()
+    );
+    let rendered = std::thread::spawn(|| THEME.get().unwrap().render("<error>!</error>").unwrap());
+    assert_eq!(rendered.join().unwrap(), "\u{1b}[31m!\u{1b}[0m");
+}
+
+impl StyleSheet<'_> {
+    /// The stylesheet in canonical syntax: variables, then one rule per selector in order, with
+    /// imports inlined. Parsing the result gives an equal stylesheet, except for the palette
+    /// and for names given to [`StyleSheet::new`] that are not valid tags, such as `my tag`.
+    pub fn to_css(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for StyleSheet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sections = Vec::new();
+        if !self.variables.is_empty() {
+            let mut variables: Vec<_> = self.variables.iter().collect();
+            variables.sort_by_key(|(name, _)| *name);
+            let mut section = ":root {\n".to_string();
+            for (name, value) in variables {
+                section.push_str(&format!("    --{}: {};\n", name, value));
+            }
+            section.push('}');
+            sections.push(section);
+        }
+        // Consecutive rules in the same `@media` block are written together.
+        let mut index = 0;
+        while index < self.rules.len() {
+            let media = &self.rules[index].media;
+            let count = self.rules[index..]
+                .iter()
+                .take_while(|rule| rule.media == *media)
+                .count();
+            let rules = &self.rules[index..index + count];
+            index += count;
+            let indent = if media.is_some() { "    " } else { "" };
+            let rules: Vec<String> = rules
+                .iter()
+                .map(|rule| {
+                    let mut text = format!("{}{} {{\n", indent, rule.selector);
+                    for declaration in &rule.declarations {
+                        text.push_str(&format!("{}    {};\n", indent, declaration));
+                    }
+                    text.push_str(indent);
+                    text.push('}');
+                    text
+                })
+                .collect();
+            match media {
+                Some(media) => {
+                    sections.push(format!("@media {} {{\n{}\n}}", media, rules.join("\n")))
+                }
+                None => sections.extend(rules),
+            }
+        }
+        write!(f, "{}", sections.join("\n\n"))
+    }
+}
+
+#[test]
+fn test_stylesheet_to_css() {
+    let source = r#":root { --accent: mix(red, #0000ff, 30%); --link: var(--accent, bright-cyan) }
+        error, warning { color: red; font-weight: bold; styles: -italic !important }
+        error path::before { content: "\"⚠\" " / "!\n"; text-transform: uppercase }
+        banner { foreground: linear-gradient(red, lighten(var(--accent), 12.5%)); width: 8 }
+        level { padding: 0 1; text-align: center; overflow: ellipsis; background: inherit }
+        diagnostic { underline-style: curly; underline-color: #ff0000; sgr: "53" }
+        empty {}
+        @media (colors >= 256) and (prefers-color-scheme: dark) and (env: CI) {
+            error { foreground: rainbow; styles: none rapid-blink font-3 }
+            * { background: default }
+        }
+        @media (output: html) { error { styles: overline } }"#;
+    let style_sheet = StyleSheet::parse(source).unwrap();
+    let css = style_sheet.to_css();
+    assert_eq!(StyleSheet::parse(&css).unwrap(), style_sheet);
+    assert!(css.starts_with(
+        ":root {\n    --accent: mix(red, #0000ff, 30%);\n    --link: var(--accent, bright-cyan);\n}\n\nerror {\n    foreground: red;\n    styles: bold;\n    styles: -italic !important;\n}"
+    ));
+    assert!(
+        css.ends_with("@media (output: html) {\n    error {\n        styles: overline;\n    }\n}")
+    );
+
+    let source =
+        "a { foreground: lighten(red, 33.333333%); background: mix(red, blue, 12.345678%) }";
+    let style_sheet = StyleSheet::parse(source).unwrap();
+    let css = style_sheet.to_css();
+    assert_eq!(StyleSheet::parse(&css).unwrap(), style_sheet);
+
+    let styles = vec![(
+        "alert",
+        vec![Styles::Bold, Styles::Underline],
+        Some(Color::TrueColor { r: 1, g: 2, b: 3 }),
+        Some(Color::BrightWhite),
+    )];
+    let style_sheet = StyleSheet::new(&styles);
+    assert_eq!(
+        style_sheet.to_css(),
+        "alert {\n    styles: bold underline;\n    foreground: #010203;\n    background: bright-white;\n}"
+    );
+    assert_eq!(
+        StyleSheet::parse(&style_sheet.to_css()).unwrap(),
+        style_sheet
+    );
+    assert_eq!(
+        style_sheet
+            .style(&["alert"], &RenderContext::default())
+            .to_string(),
+        "styles: bold underline; foreground: #010203; background: bright-white"
+    );
+
+    let styles = vec![("all-styles_2", Styles::ALL.to_vec(), None, None)];
+    let style_sheet = StyleSheet::new(&styles);
+    assert_eq!(
+        StyleSheet::parse(&style_sheet.to_css()).unwrap(),
+        style_sheet
+    );
+    // A name that is not a tag is written as it is, here as a descendant selector.
+    let style_sheet = StyleSheet::new(&[("my tag", vec![Styles::Bold], None, None)]);
+    assert_ne!(
+        StyleSheet::parse(&style_sheet.to_css()).unwrap(),
+        style_sheet
+    );
+}
+
+#[test]
+fn test_stylesheet_cascade() {
+    colored::control::set_override(true);
+    let style_sheet = StyleSheet::parse(
+        "error { foreground: red; styles: bold }
+        error { background: white }
+        error path { foreground: cyan }
+        path { foreground: blue }
+        * { background: black }
+        note { foreground: green !important }
+        note { foreground: yellow }",
+    )
+    .unwrap();
+    let context = RenderContext::default();
+    assert_eq!(
+        style_sheet.style(&["error"], &context),
+        Style::new(
+            Some(vec![Styles::Bold]),
+            Some(Color::Red),
+            Some(Color::White)
+        )
+    );
+    assert_eq!(
+        style_sheet.style(&["path"], &context),
+        Style::new(None, Some(Color::Blue), Some(Color::Black))
+    );
+    assert_eq!(
+        style_sheet.style(&["error", "path"], &context),
+        Style::new(None, Some(Color::Cyan), Some(Color::Black))
+    );
+    assert_eq!(
+        style_sheet.style(&["note"], &context).foreground,
+        Some(Color::Green)
+    );
+    assert_eq!(
+        style_sheet
+            .render("<error>a <path>b</path></error>")
+            .unwrap(),
+        "\u{1b}[1;47;31ma \u{1b}[0m\u{1b}[1;40;36mb\u{1b}[0m"
+    );
+}
+
+#[test]
+fn test_stylesheet_reset() {
+    colored::control::set_override(true);
+    let style_sheet = StyleSheet::parse(
+        "error { foreground: red; styles: bold }
+        path { foreground: default; styles: -bold dimmed }
+        quote { styles: none; background: blue }
+        reset { styles: italic }",
+    )
+    .unwrap();
+    assert_eq!(
+        style_sheet
+            .render("<error>a <path>b</path> <quote>c</quote> <reset>d</reset></error>")
+            .unwrap(),
+        "\u{1b}[1;31ma \u{1b}[0m\u{1b}[2mb\u{1b}[0m\u{1b}[1;31m \u{1b}[0m\u{1b}[44;31mc\u{1b}[0m\u{1b}[1;31m \u{1b}[0m\u{1b}[3md\u{1b}[0m"
+    );
+}
+
+#[test]
+fn test_stylesheet_pseudo_elements() {
+    colored::control::set_override(true);
+    let style_sheet = StyleSheet::parse(
+        r#"warning { foreground: yellow }
+        warning::before { content: "⚠ " / "! "; styles: bold }
+        warning::after { content: "." }"#,
+    )
+    .unwrap();
+    let mut context = RenderContext {
+        output: Output::Plain,
+        ..Default::default()
+    };
+    assert_eq!(
+        style_sheet
+            .render_with_context("<warning>Careful</warning>", &context)
+            .unwrap(),
+        "⚠ Careful."
+    );
+    context.unicode = false;
+    assert_eq!(
+        style_sheet
+            .render_with_context("<warning>Careful</warning>", &context)
+            .unwrap(),
+        "! Careful."
+    );
+    assert_eq!(
+        style_sheet.render("<warning>x</warning>").unwrap(),
+        "\u{1b}[1;33m⚠ \u{1b}[0m\u{1b}[33mx\u{1b}[0m\u{1b}[33m.\u{1b}[0m"
+    );
+}
+
+#[test]
+fn test_stylesheet_text_transform() {
+    let style_sheet = StyleSheet::parse(
+        "level { text-transform: uppercase }
+        title { text-transform: capitalize }
+        keep { text-transform: none }",
+    )
+    .unwrap();
+    let context = RenderContext {
+        output: Output::Plain,
+        ..Default::default()
+    };
+    let render = |markup| style_sheet.render_with_context(markup, &context).unwrap();
+    assert_eq!(render("<level>error</level>: straße"), "ERROR: straße");
+    assert_eq!(
+        render("<level>error <keep>code</keep></level>"),
+        "ERROR code"
+    );
+    assert_eq!(render("<title>hel<b>lo</b> world</title>"), "Hello World");
+}
+
+#[test]
+fn test_stylesheet_layout() {
+    colored::control::set_override(true);
+    let style_sheet = StyleSheet::parse(
+        r#"level { width: 5; text-align: right; text-transform: uppercase; background: blue }
+        module { width: 6; overflow: ellipsis; padding: 0 1 }
+        module::before { content: "[" }"#,
+    )
+    .unwrap();
+    let context = RenderContext {
+        output: Output::Plain,
+        ..Default::default()
+    };
+    let render = |markup| style_sheet.render_with_context(markup, &context).unwrap();
+    assert_eq!(render("<level>ßx</level>|"), "  SSX|");
+    assert_eq!(render("<module>net</module>|"), " [net   |");
+    assert_eq!(render("<module>network</module>|"), " [netw… |");
+    assert_eq!(
+        style_sheet.render("<level>a</level>").unwrap(),
+        "\u{1b}[44m    \u{1b}[0m\u{1b}[44mA\u{1b}[0m"
+    );
+}
+
+#[test]
+fn test_stylesheet_media() {
+    let style_sheet = StyleSheet::parse(
+        "error { foreground: red; styles: bold }
+        @media (colors >= 256) { error { foreground: #ff0000 } }
+        @media (env: CI) { error { styles: underline } }
+        @media (output: html) { error { background: white } }",
+    )
+    .unwrap();
+    let mut context = RenderContext::default();
+    assert_eq!(
+        style_sheet.style(&["error"], &context),
+        Style::new(Some(vec![Styles::Bold]), Some(Color::Red), None)
+    );
+
+    context.colors = 256;
+    context.env.insert("CI".to_string(), "1".to_string());
+    assert_eq!(
+        style_sheet.style(&["error"], &context),
+        Style::new(
+            Some(vec![Styles::Bold, Styles::Underline]),
+            Some(Color::TrueColor { r: 255, g: 0, b: 0 }),
+            None
+        )
+    );
+
+    context.output = Output::Plain;
+    assert_eq!(
+        style_sheet
+            .render_with_context("<error>oops</error>", &context)
+            .unwrap(),
+        "oops"
+    );
+
+    context.output = Output::Html;
+    assert_eq!(
+        style_sheet.style(&["error"], &context).background,
+        Some(Color::White)
+    );
+}
+
+#[test]
+fn test_stylesheet_variables() {
+    let mut style_sheet = StyleSheet::parse(
+        ":root { --accent: red; }
+        title { foreground: var(--accent) }
+        link { foreground: var(--link, cyan); background: var(--accent) }",
+    )
+    .unwrap();
+    let context = RenderContext::default();
+    assert_eq!(
+        style_sheet.style(&["title"], &context),
+        Style::new(None, Some(Color::Red), None)
+    );
+    assert_eq!(
+        style_sheet.style(&["link"], &context),
+        Style::new(None, Some(Color::Cyan), Some(Color::Red))
+    );
+
+    style_sheet.set_variable("--accent", Color::Green);
+    style_sheet.set_variable("link", Color::Blue);
+    assert_eq!(style_sheet.variable("accent"), Some(Color::Green));
+    assert_eq!(
+        style_sheet.style(&["title"], &context),
+        Style::new(None, Some(Color::Green), None)
+    );
+    assert_eq!(
+        style_sheet.style(&["link"], &context),
+        Style::new(None, Some(Color::Blue), Some(Color::Green))
+    );
+
+    assert!(StyleSheet::parse("title { foreground: var(--missing) }").is_err());
+}
+
+#[test]
+fn test_stylesheet_color_functions() {
+    let mut style_sheet = StyleSheet::parse(
+        ":root { --accent: red; --hover: lighten(var(--accent), 20%) }
+        link { foreground: var(--hover) }",
+    )
+    .unwrap();
+    let context = RenderContext::default();
+    let foreground = |style_sheet: &StyleSheet| style_sheet.style(&["link"], &context).foreground;
+    assert_eq!(
+        foreground(&style_sheet),
+        Some(Color::TrueColor {
+            r: 255,
+            g: 52,
+            b: 52
+        })
+    );
+    let mut palette = Palette::default();
+    palette.set(Color::Red, (255, 0, 0));
+    style_sheet.set_palette(palette);
+    assert_eq!(
+        foreground(&style_sheet),
+        Some(Color::TrueColor {
+            r: 255,
+            g: 102,
+            b: 102
+        })
+    );
+}
+
+#[test]
+fn test_stylesheet_tag_names() {
+    colored::control::set_override(true);
+    let style_sheet = StyleSheet::parse("ext-tar-gz { foreground: red }").unwrap();
+    assert_eq!(
+        style_sheet
+            .render("<ext-tar-gz>a.tar.gz</ext-tar-gz>")
+            .unwrap(),
+        "\u{1b}[31ma.tar.gz\u{1b}[0m"
+    );
+    // Since 0.2, names with digits, `-` and `_` are tags even without a rule, so they are no
+    // longer rendered as text.
+    assert_eq!(
+        style_sheet.render("see <h1> and a<b-c>, 1 <2>").unwrap(),
+        "see  and a, 1 <2>"
+    );
+}
+
+#[test]
+fn test_stylesheet_gradient() {
+    colored::control::set_override(true);
+    let style_sheet = StyleSheet::parse(
+        "banner { foreground: linear-gradient(#ff0000, #0000ff) }
+        plain { foreground: rainbow; text-transform: uppercase }
+        em { foreground: green }
+        kbd { foreground: default }
+        i { styles: italic }",
+    )
+    .unwrap();
+    let true_color = RenderContext {
+        colors: 1 << 24,
+        ..Default::default()
+    };
+    assert_eq!(
+        style_sheet
+            .render_with_context("<banner>a<em>b</em>c</banner>", &true_color)
+            .unwrap(),
+        "\u{1b}[38;2;255;0;0ma\u{1b}[0m\u{1b}[32mb\u{1b}[0m\u{1b}[38;2;0;0;255mc\u{1b}[0m"
+    );
+    assert_eq!(
+        style_sheet
+            .render_with_context("<banner>ab</banner>", &RenderContext::default())
+            .unwrap(),
+        "\u{1b}[91ma\u{1b}[0m\u{1b}[34mb\u{1b}[0m"
+    );
+    let colors_256 = RenderContext {
+        colors: 256,
+        ..Default::default()
+    };
+    assert_eq!(
+        style_sheet
+            .render_with_context("<banner>ab</banner>", &colors_256)
+            .unwrap(),
+        "\u{1b}[38;5;196ma\u{1b}[0m\u{1b}[38;5;21mb\u{1b}[0m"
+    );
+    // Tags that set their own foreground, even to the default, are not coloured by the
+    // gradient, but those that only inherit it are.
+    assert_eq!(
+        style_sheet
+            .render_with_context("<banner>a<kbd>b</kbd><i>c</i></banner>", &true_color)
+            .unwrap(),
+        "\u{1b}[38;2;255;0;0ma\u{1b}[0mb\u{1b}[3;38;2;0;0;255mc\u{1b}[0m"
+    );
+    let plain = RenderContext {
+        output: Output::Plain,
+        ..Default::default()
+    };
+    assert_eq!(
+        style_sheet
+            .render_with_context("<plain>straße</plain>", &plain)
+            .unwrap(),
+        "STRASSE"
+    );
+}
+
+#[test]
+fn test_stylesheet_layered() {
+    let defaults = StyleSheet::parse(
+        ":root { --accent: red }
+        error { foreground: var(--accent); styles: bold }
+        path { foreground: cyan }",
+    )
+    .unwrap();
+    let user = StyleSheet::parse(":root { --accent: magenta } path { styles: underline }").unwrap();
+    let overrides = StyleSheet::parse("error { styles: -bold italic }").unwrap();
+    let style_sheet = StyleSheet::layered([defaults, user, overrides]).unwrap();
+    let context = RenderContext::default();
+    assert_eq!(
+        style_sheet.style(&["error"], &context),
+        Style {
+            removed_styles: vec![Styles::Bold],
+            ..Style::new(Some(vec![Styles::Italic]), Some(Color::Magenta), None)
+        }
+    );
+    assert_eq!(
+        style_sheet.style(&["path"], &context),
+        Style::new(Some(vec![Styles::Underline]), Some(Color::Cyan), None)
+    );
+
+    let mut style_sheet = StyleSheet::parse("error { foreground: var(--accent, red) }").unwrap();
+    let cycle = StyleSheet::parse(":root { --accent: var(--accent) }").unwrap();
+    let before = style_sheet.clone();
+    assert!(style_sheet.extend(cycle).is_err());
+    assert_eq!(style_sheet, before);
+    assert_eq!(StyleSheet::layered([]).unwrap(), StyleSheet::new(&[]));
+}
+
+/// Split `key=value:key=value` into its pairs, ignoring empty ones.
+pub(crate) fn parse_assignments(s: &str) -> Result<Vec<(&str, &str)>> {
+    s.split(':')
+        .map(str::trim)
+        .filter(|assignment| !assignment.is_empty())
+        .map(|assignment| {
+            assignment
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected key=value, found {:?}", assignment))
+        })
+        .collect()
+}
+
+#[test]
+fn test_stylesheet_overrides() {
+    colored::control::set_override(true);
+    let base = || {
+        StyleSheet::parse("error { foreground: red; background: white } path { styles: bold }")
+            .unwrap()
+    };
+    let context = RenderContext::default();
+    let style_sheet = base().with_overrides("error=1;33:path=4;36:").unwrap();
+    assert_eq!(
+        style_sheet.style(&["error"], &context),
+        Style::new(
+            Some(vec![Styles::Bold]),
+            Some(Color::Yellow),
+            Some(Color::White)
+        )
+    );
+    assert_eq!(
+        style_sheet.style(&["path"], &context),
+        Style::new(
+            Some(vec![Styles::Bold, Styles::Underline]),
+            Some(Color::Cyan),
+            None
+        )
+    );
+    let style_sheet = base()
+        .with_overrides("path { styles: none; foreground: blue }")
+        .unwrap();
+    assert_eq!(
+        style_sheet.render("<path>x</path>").unwrap(),
+        "\u{1b}[34mx\u{1b}[0m"
+    );
+    assert!(base().with_overrides("error").is_err());
+    assert!(base().with_overrides("error=1;nope").is_err());
+
+    std::env::set_var("COLORED_MARKUP_TEST_COLORS", "error=32");
+    let style_sheet = base()
+        .with_env_overrides("COLORED_MARKUP_TEST_COLORS")
+        .unwrap();
+    assert_eq!(
+        style_sheet.style(&["error"], &context).foreground,
+        Some(Color::Green)
+    );
+    assert_eq!(
+        base()
+            .with_env_overrides("COLORED_MARKUP_TEST_UNSET")
+            .unwrap(),
+        base()
+    );
+    std::env::set_var("COLORED_MARKUP_TEST_COLORS", "error");
+    let error = base()
+        .with_env_overrides("COLORED_MARKUP_TEST_COLORS")
+        .unwrap_err();
+    assert!(error.to_string().contains("COLORED_MARKUP_TEST_COLORS"));
+}
+
+#[test]
+fn test_stylesheet_import() {
+    let resolver = MemoryResolver::from_iter([
+        (
+            "base.css",
+            "@import \"colors.css\"; title { foreground: var(--accent) } link { foreground: blue }",
+        ),
+        ("colors.css", ":root { --accent: red }"),
+        ("cycle.css", "@import \"cycle.css\";"),
+    ]);
+    let style_sheet = StyleSheet::parse_with_resolver(
+        "@import \"base.css\"; link { foreground: green }",
+        &resolver,
+    )
+    .unwrap();
+    let context = RenderContext::default();
+    assert_eq!(
+        style_sheet.style(&["title"], &context),
+        Style::new(None, Some(Color::Red), None)
+    );
+    assert_eq!(
+        style_sheet.style(&["link"], &context),
+        Style::new(None, Some(Color::Green), None)
+    );
+
+    assert!(StyleSheet::parse("@import \"base.css\";").is_err());
+    assert!(StyleSheet::parse_with_resolver("@import \"missing.css\";", &resolver).is_err());
+    assert!(StyleSheet::parse_with_resolver("@import \"cycle.css\";", &resolver).is_err());
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_no_styles_template() {
-        let template = StyleSheet {
-            styles: HashMap::new(),
-        };
+        let template = StyleSheet::new(&[]);
         let result = template.render("Hello <bold>World</bold><em></em>!");
         assert_eq!(result.unwrap(), "Hello World!");
     }
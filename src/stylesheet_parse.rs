@@ -1,15 +1,23 @@
+use crate::context::*;
+use crate::declarations::*;
+use crate::palette::Palette;
+use crate::selector::*;
 use crate::*;
 use anyhow::Result;
 use nom::{
     branch::alt,
-    bytes::complete::tag,
-    character::complete::{alpha1, char, multispace0},
-    combinator::{map, opt, value},
+    bytes::complete::{
+        escaped_transform, is_not, tag, tag_no_case, take_while, take_while1, take_while_m_n,
+    },
+    character::complete::{alpha1, char, multispace0, multispace1, one_of, u32},
+    combinator::{map, map_opt, map_res, opt, recognize, value, verify},
     error::ParseError,
-    multi::{many0, many1, separated_list0},
-    sequence::{delimited, tuple},
+    multi::{many0, separated_list0, separated_list1},
+    number::complete::float,
+    sequence::{delimited, pair, preceded, tuple},
     IResult, Parser,
 };
+use std::borrow::Cow;
 
 /// A combinator that takes a parser `inner` and produces a parser that also consumes both leading and
 /// trailing whitespace, returning the output of `inner`.
@@ -20,145 +28,1004 @@ where
     delimited(multispace0, inner, multispace0)
 }
 
-pub(crate) fn parse(s: &str) -> Result<Vec<(&str, Style)>> {
-    let (remaining, rules) = rules(s).or(Err(anyhow::anyhow!("Failed to parse stylesheet")))?;
+/// A top level item in a stylesheet.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Item<'a> {
+    /// A `selector, selector { ... }` rule.
+    Rule(Vec<Selector<'a>>, Vec<Declaration>),
+    /// A `:root { --name: value }` block of variable definitions.
+    Variables(Vec<(String, ColorExpr)>),
+    /// An `@import "path";` of another stylesheet.
+    Import(String),
+    /// An `@media query { ... }` block of rules that only apply when the query matches.
+    Media(MediaQuery, Vec<(Vec<Selector<'a>>, Vec<Declaration>)>),
+}
+
+impl<'a> Item<'a> {
+    pub(crate) fn into_owned(self) -> Item<'static> {
+        match self {
+            Item::Rule(selectors, declarations) => {
+                Item::Rule(selectors_into_owned(selectors), declarations)
+            }
+            Item::Variables(definitions) => Item::Variables(definitions),
+            Item::Import(path) => Item::Import(path),
+            Item::Media(query, rules) => Item::Media(
+                query,
+                rules
+                    .into_iter()
+                    .map(|(selectors, declarations)| {
+                        (selectors_into_owned(selectors), declarations)
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn selectors_into_owned(selectors: Vec<Selector>) -> Vec<Selector<'static>> {
+    selectors.into_iter().map(Selector::into_owned).collect()
+}
+
+pub(crate) fn parse(s: &str) -> Result<Vec<Item<'_>>> {
+    let (remaining, items) = items(s).or(Err(anyhow::anyhow!("Failed to parse stylesheet")))?;
     if !remaining.is_empty() {
-        return Err(anyhow::anyhow!("Failed to parse stylesheet"));
+        return Err(parse_error(s, remaining));
+    }
+    Ok(items)
+}
+
+/// An error for `s`, which only parsed up to `remaining`, naming the declaration that failed
+/// to parse and its line, or the line parsing stopped at.
+fn parse_error(s: &str, remaining: &str) -> anyhow::Error {
+    let line = |at: &str| s[..s.len() - at.len()].matches('\n').count() + 1;
+    if let Some(at) = failed_declaration(remaining) {
+        let end = at.find([';', '}', '\n']).unwrap_or(at.len());
+        return anyhow::anyhow!(
+            "Failed to parse stylesheet: unknown property or invalid value in {:?} at line {}",
+            at[..end].trim(),
+            line(at)
+        );
+    }
+    let at = remaining.trim_start();
+    anyhow::anyhow!(
+        "Failed to parse stylesheet at line {}: {:?}",
+        line(at),
+        at.lines().next().unwrap_or_default().trim()
+    )
+}
+
+/// The start of the first declaration in the rule (or `@media` block) at the start of `s`
+/// that does not parse, if any.
+fn failed_declaration(s: &str) -> Option<&str> {
+    let s = match tuple((ws(tag("@media")), media_query, ws(char('{'))))(s) {
+        Ok((rules, _)) => many0(rule)(rules).ok()?.0,
+        Err(_) => s,
+    };
+    let (mut s, _) = tuple((separated_list1(char(','), ws(selector)), ws(char('{'))))(s).ok()?;
+    loop {
+        let start = s.trim_start();
+        if start.starts_with('}') {
+            return None;
+        }
+        let Ok((rest, _)) = declaration(s) else {
+            return Some(start);
+        };
+        let rest = rest.trim_start();
+        if rest.starts_with('}') {
+            return None;
+        }
+        match rest.strip_prefix(';') {
+            Some(rest) => s = rest,
+            None => return Some(start),
+        }
     }
-    Ok(rules)
 }
 
 #[test]
 fn test_parse() {
     assert_eq!(
         parse(" r { foreground: red; }").unwrap(),
-        vec![("r", Style::new(None, Some(Color::Red), None))]
+        vec![Item::Rule(
+            vec![Selector::tag("r")],
+            vec![Property::Foreground(ColorExpr::Color(Color::Red)).into()]
+        )]
     );
 
     assert!(parse(" r { foreground: not-a-color; }").is_err());
+
+    let error = |s| parse(s).unwrap_err().to_string();
+    assert_eq!(
+        error("a { foreground: red }\nb {\n    styles: bold;\n    colour: red;\n}"),
+        "Failed to parse stylesheet: unknown property or invalid value in \"colour: red\" at line 4"
+    );
+    assert_eq!(
+        error("@media (colors >= 256) {\n  a { foreground: red }\n  b { foreground: red blue }\n}"),
+        "Failed to parse stylesheet: unknown property or invalid value in \"foreground: red blue\" at line 3"
+    );
+    assert_eq!(
+        error("a { foreground: red }\n\nb c! { styles: bold }"),
+        "Failed to parse stylesheet at line 3: \"b c! { styles: bold }\""
+    );
+}
+
+/// Parse the whole of `s` as a single value, such as a colour.
+pub(crate) fn parse_value<'a, O>(
+    s: &'a str,
+    what: &str,
+    mut parser: impl FnMut(&'a str) -> IResult<&'a str, O>,
+) -> Result<O> {
+    match parser(s.trim()) {
+        Ok(("", value)) => Ok(value),
+        _ => Err(anyhow::anyhow!("Invalid {}: {}", what, s)),
+    }
+}
+
+fn items(s: &str) -> IResult<&str, Vec<Item<'_>>> {
+    many0(alt((
+        map(import, Item::Import),
+        map(root, |definitions| {
+            let definitions = definitions
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value));
+            Item::Variables(definitions.collect())
+        }),
+        map(media, |(query, rules)| Item::Media(query, rules)),
+        map(rule, |(selectors, declarations)| {
+            Item::Rule(selectors, declarations)
+        }),
+    )))(s)
+}
+
+#[test]
+fn test_items() {
+    assert_eq!(
+        items(":root { --accent: red } alert{foreground:var(--accent)}")
+            .unwrap()
+            .1,
+        vec![
+            Item::Variables(vec![("accent".to_string(), ColorExpr::Color(Color::Red))]),
+            Item::Rule(
+                vec![Selector::tag("alert")],
+                vec![Property::Foreground(ColorExpr::Var("accent".to_string(), None)).into()]
+            )
+        ]
+    );
+}
+
+/// `@import "path";`
+fn import(s: &str) -> IResult<&str, String> {
+    map(
+        tuple((ws(tag("@import")), string, ws(char(';')))),
+        |(_, path, _)| path,
+    )(s)
+}
+
+/// A double quoted string, with `\"`, `\\` and `\n` escapes.
+fn string(s: &str) -> IResult<&str, String> {
+    let escape = alt((
+        value("\\", char('\\')),
+        value("\"", char('"')),
+        value("\n", char('n')),
+    ));
+    map(
+        delimited(
+            char('"'),
+            opt(escaped_transform(is_not("\\\""), '\\', escape)),
+            char('"'),
+        ),
+        Option::unwrap_or_default,
+    )(s)
 }
 
-fn rules(s: &str) -> IResult<&str, Vec<(&str, Style)>> {
-    many0(rule)(s)
+#[test]
+fn test_string() {
+    assert_eq!(string(r#""""#).unwrap().1, "");
+    assert_eq!(string(r#""⚠ ""#).unwrap().1, "⚠ ");
+    assert_eq!(string(r#""a \"b\" \\""#).unwrap().1, r#"a "b" \"#);
 }
 
 #[test]
-fn test_rules() {
+fn test_import() {
+    assert_eq!(import(r#"@import "base.css";"#).unwrap().1, "base.css");
     assert_eq!(
-        rules("alert{foreground:red}").unwrap().1,
-        vec![("alert", Style::new(None, Some(Color::Red), None))]
+        items(r#" @import "a.css"; @import "b.css"; r { foreground: red }"#)
+            .unwrap()
+            .1[..2],
+        [
+            Item::Import("a.css".to_string()),
+            Item::Import("b.css".to_string())
+        ]
     );
 }
 
-fn rule(s: &str) -> IResult<&str, (&str, Style)> {
+/// `@media query { rules }`
+#[allow(clippy::type_complexity)]
+fn media(s: &str) -> IResult<&str, (MediaQuery, Vec<(Vec<Selector<'_>>, Vec<Declaration>)>)> {
     map(
-        tuple((ws(selector), wrapped_declarations)),
-        |(selector, style)| (selector, style),
+        tuple((
+            ws(tag("@media")),
+            media_query,
+            ws(char('{')),
+            many0(rule),
+            ws(char('}')),
+        )),
+        |(_, query, _, rules, _)| (query, rules),
+    )(s)
+}
+
+#[test]
+fn test_media() {
+    let (query, rules) = media("@media (colors >= 256) and (env: CI) { r { foreground: red } }")
+        .unwrap()
+        .1;
+    assert_eq!(
+        query,
+        MediaQuery(vec![
+            MediaCondition::Colors(Comparison::GreaterOrEqual, 256),
+            MediaCondition::Env("CI".to_string())
+        ])
+    );
+    assert_eq!(rules.len(), 1);
+}
+
+fn media_query(s: &str) -> IResult<&str, MediaQuery> {
+    map(separated_list1(ws(tag("and")), media_condition), MediaQuery)(s)
+}
+
+fn media_condition(s: &str) -> IResult<&str, MediaCondition> {
+    let comparison = alt((
+        value(Comparison::GreaterOrEqual, tag(">=")),
+        value(Comparison::LessOrEqual, tag("<=")),
+        value(Comparison::Greater, char('>')),
+        value(Comparison::Less, char('<')),
+        value(Comparison::Equal, char('=')),
+        value(Comparison::Equal, char(':')),
+    ));
+    let colors = map(
+        tuple((tag("colors"), ws(comparison), u32)),
+        |(_, comparison, value)| MediaCondition::Colors(comparison, value),
+    );
+    let color_scheme = map(
+        preceded(
+            tuple((tag("prefers-color-scheme"), ws(char(':')))),
+            alt((
+                value(ColorScheme::Light, tag("light")),
+                value(ColorScheme::Dark, tag("dark")),
+            )),
+        ),
+        MediaCondition::ColorScheme,
+    );
+    let env = map(
+        preceded(
+            tuple((tag("env"), ws(char(':')))),
+            take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+        ),
+        |name: &str| MediaCondition::Env(name.to_string()),
+    );
+    let output = map(
+        preceded(
+            tuple((tag("output"), ws(char(':')))),
+            alt((
+                value(Output::Ansi, tag("ansi")),
+                value(Output::Plain, tag("plain")),
+                value(Output::Html, tag("html")),
+            )),
+        ),
+        MediaCondition::Output,
+    );
+    delimited(
+        ws(char('(')),
+        alt((colors, color_scheme, env, output)),
+        ws(char(')')),
     )(s)
 }
 
+#[test]
+fn test_media_condition() {
+    assert_eq!(
+        media_condition("(colors < 16)").unwrap().1,
+        MediaCondition::Colors(Comparison::Less, 16)
+    );
+    assert_eq!(
+        media_condition("( prefers-color-scheme: dark )").unwrap().1,
+        MediaCondition::ColorScheme(ColorScheme::Dark)
+    );
+    assert_eq!(
+        media_condition("(output: html)").unwrap().1,
+        MediaCondition::Output(Output::Html)
+    );
+    assert!(media_condition("(resolution: 2dppx)").is_err());
+}
+
+fn rule(s: &str) -> IResult<&str, (Vec<Selector<'_>>, Vec<Declaration>)> {
+    tuple((
+        separated_list1(char(','), ws(selector)),
+        wrapped_declarations,
+    ))(s)
+}
+
 #[test]
 fn test_rule() {
+    let red = || Property::Foreground(ColorExpr::Color(Color::Red)).into();
+    let alert = || vec![Selector::tag("alert")];
     assert_eq!(
         rule("alert{foreground:red}").unwrap().1,
-        ("alert", Style::new(None, Some(Color::Red), None))
+        (alert(), vec![red()])
     );
     assert_eq!(
         rule("alert { foreground: red; background: blue }")
             .unwrap()
             .1,
         (
-            "alert",
-            Style::new(None, Some(Color::Red), Some(Color::Blue))
+            alert(),
+            vec![
+                red(),
+                Property::Background(ColorExpr::Color(Color::Blue)).into()
+            ]
         )
     );
     assert_eq!(
         rule("alert{foreground:red;}").unwrap().1,
-        ("alert", Style::new(None, Some(Color::Red), None))
+        (alert(), vec![red()])
+    );
+    assert_eq!(
+        rule("alert, error path {foreground:red}").unwrap().1,
+        (
+            vec![
+                Selector::tag("alert"),
+                Selector::new(vec!["error".into(), "path".into()], None)
+            ],
+            vec![red()]
+        )
     );
 }
 
-fn selector(s: &str) -> IResult<&str, &str> {
-    alpha1(s)
+fn root(s: &str) -> IResult<&str, Vec<(&str, ColorExpr)>> {
+    map(
+        tuple((
+            ws(tag(":root")),
+            ws(char('{')),
+            separated_list0(char(';'), ws(variable_definition)),
+            opt(char(';')),
+            ws(char('}')),
+        )),
+        |(_, _, definitions, _, _)| definitions,
+    )(s)
+}
+
+#[test]
+fn test_root() {
+    assert_eq!(
+        root(":root { --accent: #ff8800; --error: var(--accent, red); }")
+            .unwrap()
+            .1,
+        vec![
+            (
+                "accent",
+                ColorExpr::Color(Color::TrueColor {
+                    r: 0xff,
+                    g: 0x88,
+                    b: 0x00
+                })
+            ),
+            (
+                "error",
+                ColorExpr::Var(
+                    "accent".to_string(),
+                    Some(Box::new(ColorExpr::Color(Color::Red)))
+                )
+            )
+        ]
+    );
+}
+
+fn variable_definition(s: &str) -> IResult<&str, (&str, ColorExpr)> {
+    map(
+        tuple((variable_name, ws(char(':')), color_expr)),
+        |(name, _, value)| (name, value),
+    )(s)
+}
+
+/// A `--name` custom property, returning the name without the leading dashes.
+fn variable_name(s: &str) -> IResult<&str, &str> {
+    preceded(
+        tag("--"),
+        recognize(take_while1(|c: char| {
+            c.is_ascii_alphanumeric() || c == '-' || c == '_'
+        })),
+    )(s)
+}
+
+/// A tag name: a letter followed by letters, digits, `-` or `_`.
+fn tag_name(s: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        alpha1,
+        take_while(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_'),
+    ))(s)
 }
 
-fn wrapped_declarations(s: &str) -> IResult<&str, Style> {
+/// Tag names or `*`, separated by whitespace (the descendant combinator), optionally followed
+/// by `::before` or `::after`.
+fn selector(s: &str) -> IResult<&str, Selector<'_>> {
+    let pseudo_element = alt((
+        value(PseudoElement::Before, tag("::before")),
+        value(PseudoElement::After, tag("::after")),
+    ));
+    map(
+        tuple((
+            separated_list1(multispace1, alt((tag_name, tag("*")))),
+            opt(pseudo_element),
+        )),
+        |(names, pseudo_element)| {
+            Selector::new(
+                names.into_iter().map(Cow::Borrowed).collect(),
+                pseudo_element,
+            )
+        },
+    )(s)
+}
+
+#[test]
+fn test_selector() {
+    assert_eq!(
+        selector("error path::after").unwrap().1,
+        Selector::new(
+            vec!["error".into(), "path".into()],
+            Some(PseudoElement::After)
+        )
+    );
+    assert_eq!(
+        selector("ext-tar-gz h1").unwrap().1,
+        Selector::new(vec!["ext-tar-gz".into(), "h1".into()], None)
+    );
+    assert!(selector("-x").is_err());
+}
+
+fn wrapped_declarations(s: &str) -> IResult<&str, Vec<Declaration>> {
     map(
         tuple((ws(char('{')), declarations, opt(char(';')), ws(char('}')))),
-        |(_, style, _, _)| style,
+        |(_, declarations, _, _)| declarations,
     )(s)
 }
 
 #[test]
 fn test_wrapped_declarations() {
+    let (variables, palette) = Default::default();
+    let style = |s| {
+        resolve_declarations(
+            &wrapped_declarations(s).unwrap().1,
+            false,
+            &variables,
+            &palette,
+        )
+        .unwrap()
+    };
     assert_eq!(
-        wrapped_declarations("{ foreground: red }").unwrap().1,
+        style("{ foreground: red }"),
         Style::new(None, Some(Color::Red), None)
     );
     assert_eq!(
-        wrapped_declarations("{ foreground: red; styles: bold }")
-            .unwrap()
-            .1,
+        style("{ foreground: red; styles: bold }"),
         Style::new(Some(vec![Styles::Bold]), Some(Color::Red), None)
     );
     assert_eq!(
-        wrapped_declarations("{ foreground: red; styles: bold }")
-            .unwrap()
-            .1,
+        style("{ foreground: red; styles: bold; }"),
         Style::new(Some(vec![Styles::Bold]), Some(Color::Red), None)
     );
 }
 
-fn declarations(s: &str) -> IResult<&str, Style> {
-    map(separated_list0(char(';'), declaration), |decls| {
-        let mut result = Style::default();
-        for style in decls {
-            result = result.merge(style);
-        }
-        result
+fn declarations(s: &str) -> IResult<&str, Vec<Declaration>> {
+    map(separated_list0(char(';'), declaration), |declarations| {
+        declarations.into_iter().flatten().collect()
     })(s)
 }
 
-fn declaration(s: &str) -> IResult<&str, Style> {
-    alt((color_style_declaration, styles_style_declaration))(s)
+/// A declaration, which may set more than one `Property` (e.g. `styles: none italic`).
+fn declaration(s: &str) -> IResult<&str, Vec<Declaration>> {
+    map(
+        tuple((
+            alt((
+                map(color_style_declaration, |property| vec![property]),
+                map(content_declaration, |property| vec![property]),
+                map(text_transform_declaration, |property| vec![property]),
+                map(underline_declaration, |property| vec![property]),
+                map(sgr_declaration, |property| vec![property]),
+                box_declaration,
+                css_declaration,
+                styles_style_declaration,
+            )),
+            opt(ws(tag("!important"))),
+        )),
+        |(properties, important)| {
+            let important = important.is_some();
+            properties
+                .into_iter()
+                .map(|property| Declaration {
+                    property,
+                    important,
+                })
+                .collect()
+        },
+    )(s)
 }
 
 #[test]
 fn test_declaration() {
     assert_eq!(
         color_style_declaration("foreground: red").unwrap().1,
-        Style::new(None, Some(Color::Red), None)
+        Property::Foreground(ColorExpr::Color(Color::Red))
     );
     assert_eq!(
         styles_style_declaration("styles : bold").unwrap().1,
-        Style::new(Some(vec![Styles::Bold]), None, None)
+        vec![Property::Styles(vec![Styles::Bold])]
     );
     assert_eq!(
         styles_style_declaration("styles : bold dimmed").unwrap().1,
-        Style::new(Some(vec![Styles::Bold, Styles::Dimmed]), None, None)
+        vec![Property::Styles(vec![Styles::Bold, Styles::Dimmed])]
+    );
+    assert_eq!(
+        declaration("styles: bold !important").unwrap().1,
+        vec![Declaration {
+            property: Property::Styles(vec![Styles::Bold]),
+            important: true
+        }]
+    );
+}
+
+#[test]
+fn test_keyword_declarations() {
+    assert_eq!(
+        color_style_declaration("foreground: default").unwrap().1,
+        Property::Keyword(PropertyName::Foreground, Keyword::Initial)
+    );
+    assert_eq!(
+        color_style_declaration("background: inherit").unwrap().1,
+        Property::Keyword(PropertyName::Background, Keyword::Inherit)
+    );
+    assert_eq!(
+        styles_style_declaration("styles: none").unwrap().1,
+        vec![Property::Keyword(PropertyName::Styles, Keyword::Initial)]
+    );
+    assert_eq!(
+        styles_style_declaration("styles: none italic -bold no-underline")
+            .unwrap()
+            .1,
+        vec![
+            Property::Keyword(PropertyName::Styles, Keyword::Initial),
+            Property::Styles(vec![Styles::Italic]),
+            Property::RemoveStyles(vec![Styles::Bold, Styles::Underline])
+        ]
+    );
+}
+
+fn color_style_declaration(s: &str) -> IResult<&str, Property> {
+    // `color` and `background-color` are the CSS names.
+    let name = alt((
+        value(PropertyName::Foreground, tag("foreground")),
+        value(PropertyName::Foreground, tag("color")),
+        value(PropertyName::Background, tag("background-color")),
+        value(PropertyName::Background, tag("background")),
+    ));
+    let (s, (name, _)) = tuple((ws(name), char(':')))(s)?;
+    let keyword = map(keyword, |keyword| Property::Keyword(name, keyword));
+    match name {
+        PropertyName::Foreground => ws(alt((
+            keyword,
+            map(gradient, Property::Gradient),
+            map(color_expr, Property::Foreground),
+        )))(s),
+        _ => ws(alt((keyword, map(color_expr, Property::Background))))(s),
+    }
+}
+
+/// `linear-gradient(red, blue)` with at least two colour stops, or `rainbow`.
+fn gradient(s: &str) -> IResult<&str, GradientExpr> {
+    let linear = map(
+        delimited(
+            tag("linear-gradient("),
+            verify(
+                separated_list1(char(','), ws(color_expr)),
+                |stops: &Vec<ColorExpr>| stops.len() >= 2,
+            ),
+            char(')'),
+        ),
+        GradientExpr::Linear,
     );
+    alt((linear, value(GradientExpr::Rainbow, tag("rainbow"))))(s)
 }
 
-fn color_style_declaration(s: &str) -> IResult<&str, Style> {
-    let foreground_or_background = alt((tag("foreground"), tag("background")));
+#[test]
+fn test_gradient() {
+    assert_eq!(
+        color_style_declaration("foreground: linear-gradient(#ff0000, var(--accent))")
+            .unwrap()
+            .1,
+        Property::Gradient(GradientExpr::Linear(vec![
+            ColorExpr::Color(Color::TrueColor { r: 255, g: 0, b: 0 }),
+            ColorExpr::Var("accent".to_string(), None)
+        ]))
+    );
+    assert_eq!(
+        color_style_declaration("foreground: rainbow").unwrap().1,
+        Property::Gradient(GradientExpr::Rainbow)
+    );
+    assert!(gradient("linear-gradient(red)").is_err());
+    assert!(color_style_declaration("background: rainbow").is_err());
+}
+
+/// `content: "text"` or `content: "text" / "ascii fallback"`
+fn content_declaration(s: &str) -> IResult<&str, Property> {
     map(
-        tuple((ws(foreground_or_background), char(':'), ws(color))),
-        |(attribute, _, color)| match attribute {
-            "foreground" => Style::new(None, Some(color), None),
-            "background" => Style::new(None, None, Some(color)),
-            _ => panic!(),
-        },
+        tuple((
+            ws(tag("content")),
+            char(':'),
+            ws(string),
+            opt(preceded(char('/'), ws(string))),
+        )),
+        |(_, _, text, ascii)| Property::Content(Content { text, ascii }),
+    )(s)
+}
+
+#[test]
+fn test_content_declaration() {
+    assert_eq!(
+        content_declaration(r#"content: "⚠ " / "! ""#).unwrap().1,
+        Property::Content(Content {
+            text: "⚠ ".to_string(),
+            ascii: Some("! ".to_string())
+        })
+    );
+}
+
+/// `text-transform: uppercase|lowercase|capitalize|none`
+fn text_transform_declaration(s: &str) -> IResult<&str, Property> {
+    let text_transform = alt((
+        value(TextTransform::Uppercase, tag("uppercase")),
+        value(TextTransform::Lowercase, tag("lowercase")),
+        value(TextTransform::Capitalize, tag("capitalize")),
+        value(TextTransform::None, tag("none")),
+    ));
+    map(
+        tuple((ws(tag("text-transform")), char(':'), ws(text_transform))),
+        |(_, _, text_transform)| Property::TextTransform(text_transform),
+    )(s)
+}
+
+#[test]
+fn test_text_transform_declaration() {
+    assert_eq!(
+        text_transform_declaration("text-transform: uppercase")
+            .unwrap()
+            .1,
+        Property::TextTransform(TextTransform::Uppercase)
+    );
+}
+
+/// `underline-style: curly` or `underline-color: red`
+fn underline_declaration(s: &str) -> IResult<&str, Property> {
+    let underline_style = alt((
+        value(UnderlineStyle::Single, tag("single")),
+        value(UnderlineStyle::Double, tag("double")),
+        value(UnderlineStyle::Curly, tag("curly")),
+        value(UnderlineStyle::Dotted, tag("dotted")),
+        value(UnderlineStyle::Dashed, tag("dashed")),
+    ));
+    alt((
+        map(
+            tuple((ws(tag("underline-style")), char(':'), ws(underline_style))),
+            |(_, _, underline_style)| Property::UnderlineStyle(underline_style),
+        ),
+        map(
+            tuple((ws(tag("underline-color")), char(':'), ws(color_expr))),
+            |(_, _, color)| Property::UnderlineColor(color),
+        ),
+    ))(s)
+}
+
+#[test]
+fn test_underline_declaration() {
+    assert_eq!(
+        underline_declaration("underline-style: curly").unwrap().1,
+        Property::UnderlineStyle(UnderlineStyle::Curly)
+    );
+    assert_eq!(
+        underline_declaration("underline-color: var(--error)")
+            .unwrap()
+            .1,
+        Property::UnderlineColor(ColorExpr::Var("error".to_string(), None))
+    );
+}
+
+/// `sgr: "38;5;208;53"`
+fn sgr_declaration(s: &str) -> IResult<&str, Property> {
+    let parameters = verify(string, |sgr: &str| {
+        !sgr.is_empty()
+            && sgr
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == ';' || c == ':')
+    });
+    map(
+        tuple((ws(tag("sgr")), char(':'), ws(parameters))),
+        |(_, _, sgr)| Property::Sgr(sgr),
     )(s)
 }
 
-fn styles_style_declaration(s: &str) -> IResult<&str, Style> {
+#[test]
+fn test_sgr_declaration() {
+    assert_eq!(
+        sgr_declaration(r#"sgr: "38;5;208;53""#).unwrap().1,
+        Property::Sgr("38;5;208;53".to_string())
+    );
+    assert!(sgr_declaration(r#"sgr: "\x1b[1m""#).is_err());
+}
+
+/// `width`, `text-align`, `overflow` and the `padding` properties.
+fn box_declaration(s: &str) -> IResult<&str, Vec<Property>> {
+    let columns = || map(u32, |columns| columns as usize);
+    let width = map(
+        tuple((ws(tag("width")), char(':'), ws(columns()))),
+        |(_, _, width)| vec![Property::Width(width)],
+    );
+    let text_align = map(
+        tuple((
+            ws(tag("text-align")),
+            char(':'),
+            ws(alt((
+                value(TextAlign::Left, tag("left")),
+                value(TextAlign::Right, tag("right")),
+                value(TextAlign::Center, tag("center")),
+            ))),
+        )),
+        |(_, _, text_align)| vec![Property::TextAlign(text_align)],
+    );
+    let overflow = map(
+        tuple((
+            ws(tag("overflow")),
+            char(':'),
+            ws(alt((
+                value(Overflow::Visible, tag("visible")),
+                value(Overflow::Clip, tag("clip")),
+                value(Overflow::Ellipsis, tag("ellipsis")),
+            ))),
+        )),
+        |(_, _, overflow)| vec![Property::Overflow(overflow)],
+    );
+    let padding_side = map(
+        tuple((
+            ws(alt((tag("padding-left"), tag("padding-right")))),
+            char(':'),
+            ws(columns()),
+        )),
+        |(side, _, padding)| match side {
+            "padding-left" => vec![Property::PaddingLeft(padding)],
+            _ => vec![Property::PaddingRight(padding)],
+        },
+    );
+    // The CSS shorthand takes one to four sides, of which only left and right apply to text.
+    let padding = map(
+        tuple((
+            ws(tag("padding")),
+            char(':'),
+            verify(many0(ws(columns())), |sides: &Vec<usize>| {
+                (1..=4).contains(&sides.len())
+            }),
+        )),
+        |(_, _, sides)| {
+            let (left, right) = match sides[..] {
+                [all] => (all, all),
+                [_, horizontal] | [_, horizontal, _] => (horizontal, horizontal),
+                [_, right, _, left] => (left, right),
+                _ => unreachable!(),
+            };
+            vec![Property::PaddingLeft(left), Property::PaddingRight(right)]
+        },
+    );
+    alt((width, text_align, overflow, padding_side, padding))(s)
+}
+
+#[test]
+fn test_box_declaration() {
+    assert_eq!(
+        box_declaration("width: 8").unwrap().1,
+        vec![Property::Width(8)]
+    );
+    assert_eq!(
+        box_declaration("padding: 0 1").unwrap().1,
+        vec![Property::PaddingLeft(1), Property::PaddingRight(1)]
+    );
+    assert_eq!(
+        box_declaration("padding: 0 1 0 2").unwrap().1,
+        vec![Property::PaddingLeft(2), Property::PaddingRight(1)]
+    );
+    assert_eq!(
+        box_declaration("padding-left: 3").unwrap().1,
+        vec![Property::PaddingLeft(3)]
+    );
+    assert!(box_declaration("padding: 1 2 3 4 5").is_err());
+    assert_eq!(
+        box_declaration("overflow: ellipsis").unwrap().1,
+        vec![Property::Overflow(Overflow::Ellipsis)]
+    );
+}
+
+/// `inherit`, or `initial`/`default` (which both reset to the terminal default).
+fn keyword(s: &str) -> IResult<&str, Keyword> {
+    alt((
+        value(Keyword::Inherit, tag("inherit")),
+        value(Keyword::Initial, tag("initial")),
+        value(Keyword::Initial, tag("default")),
+    ))(s)
+}
+
+/// The CSS properties `font-weight`, `font-style`, `text-decoration`, `text-decoration-style`,
+/// `text-decoration-color` and `opacity`, as the equivalent properties.
+fn css_declaration(s: &str) -> IResult<&str, Vec<Property>> {
+    fn property<'a, O>(
+        name: &'static str,
+        value: impl Parser<&'a str, O, nom::error::Error<&'a str>>,
+    ) -> impl FnMut(&'a str) -> IResult<&'a str, O> {
+        preceded(tuple((ws(tag(name)), char(':'))), ws(value))
+    }
+    let font_weight = alt((
+        // `bolder` first, as `bold` would otherwise match its start.
+        value(vec![Property::Styles(vec![Styles::Bold])], tag("bolder")),
+        value(vec![Property::Styles(vec![Styles::Bold])], tag("bold")),
+        value(vec![Property::Styles(vec![Styles::Dimmed])], tag("lighter")),
+        value(
+            vec![Property::RemoveStyles(vec![Styles::Bold, Styles::Dimmed])],
+            tag("normal"),
+        ),
+        map(u32, |weight| match weight {
+            0..=399 => vec![Property::Styles(vec![Styles::Dimmed])],
+            400..=599 => vec![Property::RemoveStyles(vec![Styles::Bold, Styles::Dimmed])],
+            _ => vec![Property::Styles(vec![Styles::Bold])],
+        }),
+    ));
+    let font_style = alt((
+        value(Property::Styles(vec![Styles::Italic]), tag("italic")),
+        value(Property::Styles(vec![Styles::Italic]), tag("oblique")),
+        value(Property::RemoveStyles(vec![Styles::Italic]), tag("normal")),
+    ));
+    let decoration = alt((
+        value(Styles::Underline, tag("underline")),
+        value(Styles::Strikethrough, tag("line-through")),
+        value(Styles::Overline, tag("overline")),
+        value(Styles::Blink, tag("blink")),
+    ));
+    let text_decoration = alt((
+        value(
+            Property::RemoveStyles(vec![
+                Styles::Underline,
+                Styles::Strikethrough,
+                Styles::Overline,
+                Styles::Blink,
+            ]),
+            tag("none"),
+        ),
+        map(separated_list1(multispace1, decoration), Property::Styles),
+    ));
+    let decoration_style = alt((
+        value(UnderlineStyle::Single, tag("solid")),
+        value(UnderlineStyle::Double, tag("double")),
+        value(UnderlineStyle::Curly, tag("wavy")),
+        value(UnderlineStyle::Dotted, tag("dotted")),
+        value(UnderlineStyle::Dashed, tag("dashed")),
+    ));
+    let opacity = map(float, |opacity| {
+        if opacity >= 1.0 {
+            Property::RemoveStyles(vec![Styles::Dimmed, Styles::Hidden])
+        } else if opacity <= 0.0 {
+            Property::Styles(vec![Styles::Hidden])
+        } else {
+            Property::Styles(vec![Styles::Dimmed])
+        }
+    });
+    alt((
+        property("font-weight", font_weight),
+        map(property("font-style", font_style), |property| {
+            vec![property]
+        }),
+        map(
+            property("text-decoration-style", decoration_style),
+            |underline_style| vec![Property::UnderlineStyle(underline_style)],
+        ),
+        map(property("text-decoration-color", color_expr), |color| {
+            vec![Property::UnderlineColor(color)]
+        }),
+        map(property("text-decoration", text_decoration), |property| {
+            vec![property]
+        }),
+        map(property("opacity", opacity), |property| vec![property]),
+    ))(s)
+}
+
+#[test]
+fn test_css_declaration() {
+    let style = |s| {
+        StyleSheet::parse(s)
+            .unwrap()
+            .style(&["a"], &Default::default())
+    };
+    assert_eq!(
+        style("a { color: red; background-color: blue; font-weight: bold }"),
+        Style::new(
+            Some(vec![Styles::Bold]),
+            Some(Color::Red),
+            Some(Color::Blue)
+        )
+    );
+    assert_eq!(
+        style("a { font-style: italic; text-decoration: underline line-through; opacity: 0.5 }"),
+        Style::new(
+            Some(vec![
+                Styles::Italic,
+                Styles::Underline,
+                Styles::Strikethrough,
+                Styles::Dimmed
+            ]),
+            None,
+            None
+        )
+    );
+    assert_eq!(
+        style("a { font-weight: bolder; }"),
+        Style::new(Some(vec![Styles::Bold]), None, None)
+    );
+    assert_eq!(
+        css_declaration("font-weight: 400").unwrap().1,
+        vec![Property::RemoveStyles(vec![Styles::Bold, Styles::Dimmed])]
+    );
+    assert_eq!(
+        css_declaration("text-decoration-style: wavy").unwrap().1,
+        vec![Property::UnderlineStyle(UnderlineStyle::Curly)]
+    );
+    assert!(css_declaration("font-weight: heavy").is_err());
+}
+
+/// `styles: [none|inherit|initial] style... -style... no-style...`
+fn styles_style_declaration(s: &str) -> IResult<&str, Vec<Property>> {
+    enum Item {
+        Add(Styles),
+        Remove(Styles),
+    }
+    let styles_keyword = alt((keyword, value(Keyword::Initial, tag("none"))));
+    let item = alt((
+        map(preceded(alt((tag("-"), tag("no-"))), styles), Item::Remove),
+        map(styles, Item::Add),
+    ));
     map(
-        tuple((ws(tag("styles")), char(':'), many1(ws(styles)))),
-        |(_, _, styles)| Style::new(Some(styles), None, None),
+        tuple((
+            ws(tag("styles")),
+            char(':'),
+            verify(
+                tuple((opt(ws(styles_keyword)), many0(ws(item)))),
+                |(keyword, items): &(_, Vec<_>)| keyword.is_some() || !items.is_empty(),
+            ),
+        )),
+        |(_, _, (keyword, items))| {
+            let mut properties = Vec::new();
+            if let Some(keyword) = keyword {
+                properties.push(Property::Keyword(PropertyName::Styles, keyword));
+            }
+            let (mut added, mut removed) = (Vec::new(), Vec::new());
+            for item in items {
+                match item {
+                    Item::Add(style) => added.push(style),
+                    Item::Remove(style) => removed.push(style),
+                }
+            }
+            if !added.is_empty() {
+                properties.push(Property::Styles(added));
+            }
+            if !removed.is_empty() {
+                properties.push(Property::RemoveStyles(removed));
+            }
+            properties
+        },
     )(s)
 }
 
-fn styles(s: &str) -> IResult<&str, Styles> {
+pub(crate) fn styles(s: &str) -> IResult<&str, Styles> {
     alt((
         value(Styles::Bold, tag("bold")),
         value(Styles::Dimmed, tag("dimmed")),
@@ -168,15 +1035,225 @@ fn styles(s: &str) -> IResult<&str, Styles> {
         value(Styles::Blink, tag("blink")),
         value(Styles::Hidden, tag("hidden")),
         value(Styles::Strikethrough, tag("strikethrough")),
+        value(Styles::RapidBlink, tag("rapid-blink")),
+        map_opt(preceded(tag("font-"), one_of("123456789")), |font| {
+            AlternateFont::new(font as u8 - b'0').map(Styles::Font)
+        }),
+        value(Styles::Fraktur, tag("fraktur")),
+        value(Styles::Framed, tag("framed")),
+        value(Styles::Encircled, tag("encircled")),
+        value(Styles::Overline, tag("overline")),
+        value(Styles::Superscript, tag("superscript")),
+        value(Styles::Subscript, tag("subscript")),
     ))(s)
 }
 #[test]
 fn test_styles() {
     assert_eq!(styles("bold").unwrap().1, Styles::Bold);
+    assert_eq!(styles("rapid-blink").unwrap().1, Styles::RapidBlink);
+    assert_eq!(
+        styles("font-2").unwrap().1,
+        Styles::Font(AlternateFont::new(2).unwrap())
+    );
+    assert!(styles("font-0").is_err());
     assert!(styles("invalid").is_err());
 }
 
-fn color(s: &str) -> IResult<&str, Color> {
+fn color_expr(s: &str) -> IResult<&str, ColorExpr> {
+    alt((
+        var,
+        color_function,
+        palette_color,
+        map(color, ColorExpr::Color),
+    ))(s)
+}
+
+/// `palette(name)` for any name [`Palette::get`] knows, or a base16 name such as `base08`.
+fn palette_color(s: &str) -> IResult<&str, ColorExpr> {
+    let name = verify(
+        take_while1(|c: char| c.is_ascii_alphanumeric() || c == '-'),
+        |name: &str| Palette::default().get(name).is_some(),
+    );
+    let base16 = recognize(pair(
+        tag_no_case("base0"),
+        take_while_m_n(1, 1, |c: char| c.is_ascii_hexdigit()),
+    ));
+    map(
+        alt((delimited(tag("palette("), ws(name), char(')')), base16)),
+        |name: &str| ColorExpr::Palette(name.to_ascii_lowercase()),
+    )(s)
+}
+
+#[test]
+fn test_palette_color() {
+    let palette = |name: &str| ColorExpr::Palette(name.to_string());
+    assert_eq!(color_expr("palette(red)").unwrap().1, palette("red"));
+    assert_eq!(
+        color_expr("palette( Background )").unwrap().1,
+        palette("background")
+    );
+    assert_eq!(color_expr("base0A").unwrap().1, palette("base0a"));
+    assert_eq!(
+        color_expr("lighten(base08, 10%)").unwrap().1,
+        ColorExpr::Adjust(Adjustment::Lighten, Box::new(palette("base08")), 0.1)
+    );
+    assert!(color_expr("palette(nope)").is_err());
+    assert!(color_expr("palette(#ff0000)").is_err());
+}
+
+/// `lighten(color, 20%)`, `darken`, `saturate`, `desaturate`, `alpha`, or
+/// `mix(color, color, 30%)` where the amount defaults to 50%.
+fn color_function(s: &str) -> IResult<&str, ColorExpr> {
+    let adjustment = alt((
+        value(Adjustment::Lighten, tag("lighten(")),
+        value(Adjustment::Darken, tag("darken(")),
+        value(Adjustment::Saturate, tag("saturate(")),
+        value(Adjustment::Desaturate, tag("desaturate(")),
+        value(Adjustment::Alpha, tag("alpha(")),
+    ));
+    let adjust = map(
+        tuple((
+            adjustment,
+            ws(color_expr),
+            char(','),
+            ws(percentage),
+            char(')'),
+        )),
+        |(adjustment, color, _, amount, _)| ColorExpr::Adjust(adjustment, Box::new(color), amount),
+    );
+    let mix = map(
+        tuple((
+            tag("mix("),
+            ws(color_expr),
+            char(','),
+            ws(color_expr),
+            opt(preceded(char(','), ws(percentage))),
+            char(')'),
+        )),
+        |(_, first, _, second, weight, _)| {
+            ColorExpr::Mix(Box::new(first), Box::new(second), weight.unwrap_or(0.5))
+        },
+    );
+    alt((adjust, mix))(s)
+}
+
+/// `20%`, as a fraction. Like CSS, colour functions only accept 0% to 100%.
+fn percentage(s: &str) -> IResult<&str, f32> {
+    map_opt(tuple((float, char('%'))), |(percentage, _)| {
+        (0.0..=100.0)
+            .contains(&percentage)
+            .then_some(percentage / 100.0)
+    })(s)
+}
+
+#[test]
+fn test_color_function() {
+    assert_eq!(
+        color_expr("lighten(var(--accent), 20%)").unwrap().1,
+        ColorExpr::Adjust(
+            Adjustment::Lighten,
+            Box::new(ColorExpr::Var("accent".to_string(), None)),
+            0.2
+        )
+    );
+    assert_eq!(
+        color_expr("mix(#ff0000, darken(blue, 10%))").unwrap().1,
+        ColorExpr::Mix(
+            Box::new(ColorExpr::Color(Color::TrueColor { r: 255, g: 0, b: 0 })),
+            Box::new(ColorExpr::Adjust(
+                Adjustment::Darken,
+                Box::new(ColorExpr::Color(Color::Blue)),
+                0.1
+            )),
+            0.5
+        )
+    );
+    assert!(color_expr("lighten(red)").is_err());
+    assert!(color_expr("lighten(red, -300%)").is_err());
+    assert!(color_expr("mix(red, blue, 300%)").is_err());
+    assert!(color_expr("mix(red, blue, 100%)").is_ok());
+}
+
+/// `var(--name)` or `var(--name, fallback)`.
+fn var(s: &str) -> IResult<&str, ColorExpr> {
+    map(
+        tuple((
+            tag("var("),
+            ws(variable_name),
+            opt(preceded(char(','), ws(color_expr))),
+            char(')'),
+        )),
+        |(_, name, fallback, _)| ColorExpr::Var(name.to_string(), fallback.map(Box::new)),
+    )(s)
+}
+
+#[test]
+fn test_var() {
+    assert_eq!(
+        var("var(--accent)").unwrap().1,
+        ColorExpr::Var("accent".to_string(), None)
+    );
+    assert_eq!(
+        var("var( --accent , yellow )").unwrap().1,
+        ColorExpr::Var(
+            "accent".to_string(),
+            Some(Box::new(ColorExpr::Color(Color::Yellow)))
+        )
+    );
+}
+
+pub(crate) fn color(s: &str) -> IResult<&str, Color> {
+    alt((hex_color, named_color))(s)
+}
+
+/// A `#rrggbb` or `#rgb` colour.
+fn hex_color(s: &str) -> IResult<&str, Color> {
+    fn hex_digit(s: &str) -> IResult<&str, u8> {
+        map_res(take_while_m_n(1, 1, |c: char| c.is_ascii_hexdigit()), |d| {
+            u8::from_str_radix(d, 16)
+        })(s)
+    }
+    fn long(s: &str) -> IResult<&str, Color> {
+        let component = || map(tuple((hex_digit, hex_digit)), |(h, l)| h * 16 + l);
+        map(
+            tuple((component(), component(), component())),
+            |(r, g, b)| Color::TrueColor { r, g, b },
+        )(s)
+    }
+    fn short(s: &str) -> IResult<&str, Color> {
+        map(tuple((hex_digit, hex_digit, hex_digit)), |(r, g, b)| {
+            Color::TrueColor {
+                r: r * 17,
+                g: g * 17,
+                b: b * 17,
+            }
+        })(s)
+    }
+    preceded(char('#'), alt((long, short)))(s)
+}
+
+#[test]
+fn test_hex_color() {
+    assert_eq!(
+        hex_color("#ff8800").unwrap().1,
+        Color::TrueColor {
+            r: 0xff,
+            g: 0x88,
+            b: 0x00
+        }
+    );
+    assert_eq!(
+        hex_color("#f80").unwrap().1,
+        Color::TrueColor {
+            r: 0xff,
+            g: 0x88,
+            b: 0x00
+        }
+    );
+    assert!(hex_color("#ff").is_err());
+}
+
+fn named_color(s: &str) -> IResult<&str, Color> {
     alt((
         value(Color::Black, tag("black")),
         value(Color::Red, tag("red")),
@@ -0,0 +1,204 @@
+//! Built-in themes for the standard tags of the default stylesheet.
+use anyhow::{anyhow, Result};
+
+use crate::declarations::{ColorExpr, Property};
+use crate::selector::Selector;
+use crate::styles::Color;
+use crate::StyleSheet;
+
+/// The colours of a theme, for `code`, `path`, `url`, `error`, `warn`, `info`, `success`,
+/// `hint`, `num`, `key` and `value` in that order.
+type Colors = [&'static str; 11];
+
+struct Theme {
+    name: &'static str,
+    dark: Colors,
+    light: Option<Colors>,
+}
+
+const THEMES: [Theme; 5] = [
+    Theme {
+        name: "solarized",
+        dark: [
+            "#b58900", "#2aa198", "#268bd2", "#dc322f", "#cb4b16", "#268bd2", "#859900", "#586e75",
+            "#d33682", "#6c71c4", "#859900",
+        ],
+        light: Some([
+            "#b58900", "#2aa198", "#268bd2", "#dc322f", "#cb4b16", "#268bd2", "#859900", "#93a1a1",
+            "#d33682", "#6c71c4", "#859900",
+        ]),
+    },
+    Theme {
+        name: "gruvbox",
+        dark: [
+            "#fabd2f", "#8ec07c", "#83a598", "#fb4934", "#fe8019", "#83a598", "#b8bb26", "#928374",
+            "#d3869b", "#83a598", "#b8bb26",
+        ],
+        light: Some([
+            "#b57614", "#427b58", "#076678", "#9d0006", "#af3a03", "#076678", "#79740e", "#7c6f64",
+            "#8f3f71", "#076678", "#79740e",
+        ]),
+    },
+    Theme {
+        name: "nord",
+        dark: [
+            "#ebcb8b", "#88c0d0", "#81a1c1", "#bf616a", "#d08770", "#81a1c1", "#a3be8c", "#616e88",
+            "#b48ead", "#88c0d0", "#a3be8c",
+        ],
+        light: None,
+    },
+    Theme {
+        name: "dracula",
+        dark: [
+            "#f1fa8c", "#8be9fd", "#bd93f9", "#ff5555", "#ffb86c", "#8be9fd", "#50fa7b", "#6272a4",
+            "#bd93f9", "#ff79c6", "#50fa7b",
+        ],
+        light: None,
+    },
+    Theme {
+        name: "high-contrast",
+        dark: [
+            "#ffff00", "#00ffff", "#5fafff", "#ff5f5f", "#ffaf00", "#5fafff", "#00ff00", "#d0d0d0",
+            "#ff87ff", "#ffffff", "#00ff00",
+        ],
+        light: Some([
+            "#875f00", "#005f87", "#0000d7", "#d70000", "#af5f00", "#0000d7", "#005f00", "#3a3a3a",
+            "#870087", "#000000", "#005f00",
+        ]),
+    },
+];
+
+const TAGS: [&str; 11] = [
+    "code", "path", "url", "error", "warn", "info", "success", "hint", "num", "key", "value",
+];
+
+fn color_rules(colors: &Colors) -> String {
+    let rules = TAGS
+        .iter()
+        .zip(colors)
+        .map(|(tag, color)| format!("{} {{ foreground: {} }}\n", tag, color));
+    rules.collect()
+}
+
+impl StyleSheet<'static> {
+    /// The names [`StyleSheet::theme`] accepts.
+    pub const THEMES: [&'static str; 11] = [
+        "solarized",
+        "solarized-dark",
+        "solarized-light",
+        "gruvbox",
+        "gruvbox-dark",
+        "gruvbox-light",
+        "nord",
+        "dracula",
+        "high-contrast",
+        "high-contrast-dark",
+        "high-contrast-light",
+    ];
+
+    /// A built-in theme for the tags of the [default stylesheet](crate::DEFAULT_STYLE_SHEET):
+    /// `solarized`, `gruvbox`, `nord`, `dracula` or `high-contrast`.
+    ///
+    /// Themes with light and dark variants use the light one when the [`RenderContext`]'s
+    /// colour scheme is light, and the dark one otherwise. Add `-dark` or `-light` to the name
+    /// to always use one of them. Application rules can be layered on top with
+    /// [`StyleSheet::with_overrides`] or [`StyleSheet::extend`].
+    ///
+    /// [`RenderContext`]: crate::context::RenderContext
+    pub fn theme(name: &str) -> Result<StyleSheet<'static>> {
+        let (base, variant) = match name.rsplit_once('-') {
+            Some((base, variant @ ("dark" | "light"))) => (base, Some(variant)),
+            _ => (name, None),
+        };
+        let theme = THEMES
+            .iter()
+            .find(|theme| theme.name == base)
+            .filter(|theme| theme.light.is_some() || variant != Some("light"))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown theme {}, expected one of {}",
+                    name,
+                    StyleSheet::THEMES.join(", ")
+                )
+            })?;
+        let colors = match variant {
+            Some("light") => theme.light.as_ref().unwrap_or(&theme.dark),
+            _ => &theme.dark,
+        };
+        // The default stylesheet with its foregrounds replaced, so nothing is declared twice.
+        let mut style_sheet = StyleSheet::default();
+        for (tag, color) in TAGS.iter().zip(colors) {
+            let foreground = Property::Foreground(ColorExpr::Color(color.parse::<Color>()?));
+            let rule = style_sheet
+                .rules
+                .iter_mut()
+                .find(|rule| rule.media.is_none() && rule.selector == Selector::tag(*tag))
+                .ok_or_else(|| anyhow!("The default stylesheet has no rule for {}", tag))?;
+            let declarations = &mut rule.declarations;
+            match declarations
+                .iter()
+                .position(|d| d.property.name() == "foreground")
+            {
+                Some(index) => declarations[index].property = foreground,
+                None => declarations.insert(0, foreground.into()),
+            }
+        }
+        style_sheet.resolve()?;
+        if let (None, Some(light)) = (variant, &theme.light) {
+            let light = format!(
+                "@media (prefers-color-scheme: light) {{\n{}}}\n",
+                color_rules(light)
+            );
+            style_sheet.extend(StyleSheet::parse_owned(light)?)?;
+        }
+        Ok(style_sheet)
+    }
+}
+
+#[test]
+fn test_theme() {
+    use crate::context::*;
+    use crate::styles::*;
+
+    let dark = RenderContext::default();
+    let light = RenderContext {
+        color_scheme: Some(ColorScheme::Light),
+        ..Default::default()
+    };
+    let error = |style_sheet: &StyleSheet, context| style_sheet.style(&["error"], context);
+    let true_color = |r, g, b| Some(Color::TrueColor { r, g, b });
+
+    let gruvbox = StyleSheet::theme("gruvbox").unwrap();
+    assert_eq!(
+        error(&gruvbox, &dark).foreground,
+        true_color(0xfb, 0x49, 0x34)
+    );
+    assert_eq!(
+        error(&gruvbox, &light).foreground,
+        true_color(0x9d, 0x00, 0x06)
+    );
+    assert_eq!(error(&gruvbox, &light).styles, vec![Styles::Bold]);
+    let gruvbox_dark = StyleSheet::theme("gruvbox-dark").unwrap();
+    assert_eq!(
+        error(&gruvbox_dark, &light).foreground,
+        true_color(0xfb, 0x49, 0x34)
+    );
+
+    for name in StyleSheet::THEMES {
+        let style_sheet = StyleSheet::theme(name).unwrap();
+        for tag in TAGS {
+            assert!(style_sheet.style(&[tag], &dark).foreground.is_some());
+        }
+        assert_eq!(
+            style_sheet.style(&["em"], &dark).styles,
+            vec![Styles::Italic]
+        );
+        assert_eq!(
+            style_sheet.style(&["hint"], &dark).styles,
+            StyleSheet::default().style(&["hint"], &dark).styles
+        );
+        assert_eq!(style_sheet.lint(&[]), vec![], "{}", name);
+    }
+    assert!(StyleSheet::theme("nord-light").is_err());
+    assert!(StyleSheet::theme("zenburn").is_err());
+}
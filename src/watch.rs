@@ -0,0 +1,220 @@
+//! Stylesheets loaded from files and reloaded when the files change.
+use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use crate::resolver::*;
+use crate::StyleSheet;
+
+/// A resolver that remembers the files it tries to load, whether or not they can be read.
+struct RecordingResolver {
+    inner: FileSystemResolver,
+    paths: RefCell<Vec<PathBuf>>,
+}
+
+impl StyleSheetResolver for RecordingResolver {
+    fn resolve(&self, path: &str, importer: Option<&str>) -> Result<ResolvedImport> {
+        self.paths
+            .borrow_mut()
+            .push(self.inner.path(path, importer));
+        self.inner.resolve(path, importer)
+    }
+}
+
+/// Load the stylesheet at `path` with its imports, returning it and the paths of every file
+/// it was loaded from.
+pub(crate) fn load(path: &Path) -> Result<(StyleSheet<'static>, Vec<PathBuf>)> {
+    let (style_sheet, paths) = try_load(path);
+    Ok((style_sheet?, paths))
+}
+
+/// Try to load the stylesheet at `path` with its imports, returning the result and the paths
+/// of every file read, or tried, along the way.
+fn try_load(path: &Path) -> (Result<StyleSheet<'static>>, Vec<PathBuf>) {
+    let resolver = RecordingResolver {
+        inner: FileSystemResolver::new(path.parent().unwrap_or(Path::new(""))),
+        paths: RefCell::new(Vec::new()),
+    };
+    let style_sheet = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))
+        .and_then(|source| {
+            StyleSheet::parse_owned_with_resolver(source, &resolver)
+                .with_context(|| format!("Failed to load {}", path.display()))
+        });
+    let mut paths = vec![path.to_path_buf()];
+    paths.extend(resolver.paths.into_inner());
+    (style_sheet, paths)
+}
+
+/// What a file looked like when last checked, or `None` if it could not be read.
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn stamps(paths: Vec<PathBuf>) -> Vec<(PathBuf, Stamp)> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let stamp = stamp(&path);
+            (path, stamp)
+        })
+        .collect()
+}
+
+/// The polling thread's state: the stylesheet's files and how they looked when last loaded.
+struct Poller<F> {
+    path: PathBuf,
+    watched: Vec<(PathBuf, Stamp)>,
+    current: Arc<RwLock<Arc<StyleSheet<'static>>>>,
+    on_error: F,
+}
+
+impl<F: FnMut(anyhow::Error)> Poller<F> {
+    /// Reload the stylesheet if any of its files changed since it was last loaded.
+    fn poll(&mut self) {
+        if self.watched.iter().all(|(path, old)| stamp(path) == *old) {
+            return;
+        }
+        // Watch the files this attempt read even if it failed, including imports it added,
+        // so fixing any of them is noticed, but only report each broken version once.
+        let (style_sheet, paths) = try_load(&self.path);
+        self.watched = stamps(paths);
+        match style_sheet {
+            Ok(style_sheet) => *self.current.write().unwrap() = Arc::new(style_sheet),
+            Err(error) => (self.on_error)(error),
+        }
+    }
+}
+
+/// A stylesheet loaded from a file, reloaded in the background whenever the file or one of its
+/// `@import`s changes.
+///
+/// The files are polled, by default twice a second. A new version replaces the old one
+/// atomically: [`WatchedStyleSheet::current`] returns either the old stylesheet or the new one,
+/// never a mixture. If a new version fails to load, the error is passed to a callback and the
+/// last good version stays active.
+pub struct WatchedStyleSheet {
+    current: Arc<RwLock<Arc<StyleSheet<'static>>>>,
+    /// Dropped to stop the polling thread.
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchedStyleSheet {
+    /// Load the stylesheet at `path` and watch it for changes, calling `on_error` whenever a
+    /// changed version cannot be loaded. Fails if the first version cannot be loaded.
+    pub fn new(
+        path: impl Into<PathBuf>,
+        on_error: impl FnMut(anyhow::Error) + Send + 'static,
+    ) -> Result<WatchedStyleSheet> {
+        WatchedStyleSheet::with_interval(path, Duration::from_millis(500), on_error)
+    }
+
+    /// Like [`WatchedStyleSheet::new`], checking for changes every `interval`.
+    pub fn with_interval(
+        path: impl Into<PathBuf>,
+        interval: Duration,
+        on_error: impl FnMut(anyhow::Error) + Send + 'static,
+    ) -> Result<WatchedStyleSheet> {
+        let path = path.into();
+        let (style_sheet, paths) = load(&path)?;
+        let current = Arc::new(RwLock::new(Arc::new(style_sheet)));
+        let mut poller = Poller {
+            path,
+            watched: stamps(paths),
+            current: current.clone(),
+            on_error,
+        };
+        let (stop, stopped) = channel::<()>();
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                poller.poll();
+            }
+        });
+        Ok(WatchedStyleSheet {
+            current,
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+
+    /// The most recently loaded version of the stylesheet.
+    pub fn current(&self) -> Arc<StyleSheet<'static>> {
+        self.current.read().unwrap().clone()
+    }
+}
+
+impl Drop for WatchedStyleSheet {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[test]
+fn test_watched_style_sheet() {
+    colored::control::set_override(true);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("theme.css");
+    let write = |name: &str, source: &str| fs::write(dir.path().join(name), source).unwrap();
+    write(
+        "theme.css",
+        "@import \"colors.css\"; error { foreground: var(--error) }",
+    );
+    write("colors.css", ":root { --error: red }");
+
+    let (style_sheet, paths) = load(&path).unwrap();
+    let (errors, received) = channel();
+    let mut poller = Poller {
+        path: path.clone(),
+        watched: stamps(paths),
+        current: Arc::new(RwLock::new(Arc::new(style_sheet))),
+        on_error: move |error: anyhow::Error| errors.send(error.to_string()).unwrap(),
+    };
+    let render = |poller: &Poller<_>| {
+        let current = poller.current.read().unwrap().clone();
+        current.render("<error>!</error>").unwrap()
+    };
+    assert_eq!(render(&poller), "\u{1b}[31m!\u{1b}[0m");
+
+    // A change to an import is picked up.
+    write("colors.css", ":root { --error: bright-red }");
+    poller.poll();
+    assert_eq!(render(&poller), "\u{1b}[91m!\u{1b}[0m");
+
+    // A broken version is reported once, and the last good one stays active.
+    write(
+        "theme.css",
+        "@import \"colors.css\"; @import \"extra.css\"; error { foreground: var(--error) }",
+    );
+    write("extra.css", "error { styles: nonsense }");
+    poller.poll();
+    assert!(received.try_recv().unwrap().contains("theme.css"));
+    assert_eq!(render(&poller), "\u{1b}[91m!\u{1b}[0m");
+    poller.poll();
+    assert!(received.try_recv().is_err());
+
+    // Fixing the import the broken version added is noticed.
+    write("extra.css", "error { styles: bold }");
+    poller.poll();
+    assert_eq!(render(&poller), "\u{1b}[1;91m!\u{1b}[0m");
+    assert!(received.try_recv().is_err());
+
+    let watched = WatchedStyleSheet::new(&path, |_| {}).unwrap();
+    assert_eq!(
+        watched.current().render("<error>!</error>").unwrap(),
+        "\u{1b}[1;91m!\u{1b}[0m"
+    );
+    drop(watched);
+    assert!(WatchedStyleSheet::new("/nonexistent/theme.css", |_| {}).is_err());
+}
@@ -0,0 +1,21 @@
+//! `render` detects its context from the process's environment and colour override, so it is
+//! tested in its own process, where changing them cannot affect other tests.
+use colored_markup::StyleSheet;
+
+#[test]
+fn test_render_follows_override() {
+    std::env::set_var("TERM", "xterm-256color");
+    std::env::remove_var("COLORTERM");
+    let style_sheet = StyleSheet::parse(
+        "error { foreground: red } @media (colors >= 256) { error { foreground: #123456 } }",
+    )
+    .unwrap();
+
+    colored::control::set_override(false);
+    assert_eq!(style_sheet.render("<error>!</error>").unwrap(), "!");
+    colored::control::set_override(true);
+    assert_eq!(
+        style_sheet.render("<error>!</error>").unwrap(),
+        "\u{1b}[38;2;18;52;86m!\u{1b}[0m"
+    );
+}